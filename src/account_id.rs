@@ -0,0 +1,150 @@
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+};
+use near_account_id::AccountId;
+
+use crate::config::NearNetwork;
+
+/// Path extractor for routes with a single account ID parameter. Some clients and proxies
+/// percent-encode `.` and `-` in the path (e.g. `alice%2Enear`), which [`Path`] already decodes.
+/// The ID is decoded only once, so `alice%252Enear` is rejected rather than read as `alice.near`.
+pub(crate) struct AccountIdPath(pub AccountId);
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AccountIdPath {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(raw) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        raw.parse().map(Self).map_err(|_| StatusCode::BAD_REQUEST)
+    }
+}
+
+/// NEAR implicit accounts are the 64-character lowercase hex encoding of an ED25519 public key.
+///
+/// Implicit accounts are not resolved to a named account holding the same key: the NEAR RPC can
+/// only list the access keys of a known account, and has no lookup from a key to the accounts it
+/// belongs to, so there is no named equivalent to look up in Airtable as well.
+pub(crate) fn is_implicit_account(account_id: &AccountId) -> bool {
+    account_id.get_account_type() == near_account_id::AccountType::NearImplicitAccount
+}
+
+/// Airtable formula matching the records of the account, built from the configured template if
+/// any. Hex-encoded implicit accounts are sometimes entered in uppercase, so by default they are
+/// compared case-insensitively.
+pub(crate) fn airtable_filter_formula(account_id: &AccountId, template: Option<&str>) -> String {
+    if let Some(template) = template {
+        return render_filter_template(template, account_id.as_str())
+            .expect("the filter template is validated on startup");
+    }
+    if is_implicit_account(account_id) {
+        format!("LOWER({{near_wallet}})='{account_id}'")
+    } else {
+        format!("{{near_wallet}}='{account_id}'")
+    }
+}
+
+/// Substitutes `{account_id}` in a format-string-like template, where `{{` and `}}` stand for
+/// literal braces (e.g. `{{near_wallet}}='{account_id}'`). The account ID is escaped for use in an
+/// Airtable string literal.
+pub(crate) fn render_filter_template(template: &str, account_id: &str) -> Result<String, String> {
+    let escaped_account_id = account_id
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('"', "\\\"");
+    let mut formula = String::new();
+    let mut has_account_id = false;
+    let mut chars = template.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '{' if chars.next_if_eq(&'{').is_some() => formula.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => formula.push('}'),
+            '{' => {
+                let placeholder = chars
+                    .by_ref()
+                    .take_while(|char| *char != '}')
+                    .collect::<String>();
+                if placeholder != "account_id" {
+                    return Err(format!(
+                        "unknown placeholder `{{{placeholder}}}`, literal braces are written as `{{{{` and `}}}}`"
+                    ));
+                }
+                formula.push_str(&escaped_account_id);
+                has_account_id = true;
+            }
+            '}' => {
+                return Err("unmatched `}`, literal braces are written as `{{` and `}}`".to_string())
+            }
+            char => formula.push(char),
+        }
+    }
+    if !has_account_id {
+        return Err("does not contain `{account_id}`".to_string());
+    }
+    Ok(formula)
+}
+
+/// Implicit accounts exist on every network, while named accounts end with the network top-level
+/// account (`.testnet` on testnet, anything else on mainnet).
+pub(crate) fn belongs_to_network(account_id: &AccountId, network: NearNetwork) -> bool {
+    if account_id.get_account_type().is_implicit() {
+        return true;
+    }
+    let is_testnet_account =
+        account_id.as_str() == "testnet" || account_id.as_str().ends_with(".testnet");
+    is_testnet_account == (network == NearNetwork::Testnet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        mock_airtable::{self, MockAirtable},
+    };
+
+    const REGEX_TEMPLATE: &str = "REGEX_MATCH({{Wallet Address}}, '(^|,){account_id}(,|$)')";
+
+    #[tokio::test]
+    async fn lookups_use_the_filter_template() {
+        let airtable = MockAirtable::start().await;
+        let (_, router) = airtable.app(Config {
+            airtable_filter_template: Some(REGEX_TEMPLATE.to_string()),
+            ..airtable.config()
+        });
+
+        mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        let requests = airtable.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].query_parameter("filterByFormula").as_deref(),
+            Some("REGEX_MATCH({Wallet Address}, '(^|,)alice.near(,|$)')")
+        );
+    }
+
+    #[test]
+    fn substituted_values_cannot_close_the_string_literal() {
+        assert_eq!(
+            render_filter_template("{{near_wallet}}='{account_id}'", "x' OR TRUE() OR '").unwrap(),
+            r"{near_wallet}='x\' OR TRUE() OR \''"
+        );
+        assert_eq!(
+            render_filter_template("{{near_wallet}}=\"{account_id}\"", r#"x\" OR "1"#).unwrap(),
+            r#"{near_wallet}="x\\\" OR \"1""#
+        );
+    }
+
+    #[test]
+    fn double_braces_are_literal_braces() {
+        assert_eq!(
+            render_filter_template("{{{{{account_id}}}}}", "alice.near").unwrap(),
+            "{{alice.near}}"
+        );
+        assert!(render_filter_template("{{near_wallet}}='x'", "alice.near").is_err());
+        assert!(render_filter_template("{near_wallet}='{account_id}'", "alice.near").is_err());
+        assert!(render_filter_template("{{near_wallet}='{account_id}'", "alice.near").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{AirtableApiVersion, Config},
+    AppState, KycError,
+};
+
+/// Source of the KYC records, so the lookups can be tested without an Airtable API.
+#[axum::async_trait]
+pub(crate) trait AirtableProvider: Send + Sync {
+    /// Lists the records of the KYC table with the list records query parameters, e.g.
+    /// `filterByFormula`, returning the response body.
+    async fn query_records(
+        &self,
+        state: &AppState,
+        query: &[(&str, &str)],
+    ) -> Result<serde_json::Value, KycError>;
+}
+
+/// Builds the Airtable API requests against the configured `AIRTABLE_API_VERSION`. The
+/// connections are pooled across requests.
+pub(crate) struct AirtableHttpClient {
+    client: reqwest::Client,
+    base_url: String,
+    base_id: String,
+    /// Swapped on key rotation; requests already built keep the key they were built with. A lock
+    /// stands in for an atomic swap, as it is only held to clone the `Arc`.
+    api_key: RwLock<Arc<str>>,
+    /// The key before the last rotation and when it was rotated, which is still tried for the
+    /// requests the new key is rejected for until the grace period is over.
+    previous_api_key: RwLock<Option<(Arc<str>, Instant)>>,
+    key_rotation_grace_period: Duration,
+    api_version: AirtableApiVersion,
+}
+
+impl AirtableHttpClient {
+    pub(crate) fn new(config: &Config) -> Self {
+        let mut client = reqwest::Client::builder();
+        if let Some(proxy) = &config.airtable_proxy {
+            client = client.proxy(proxy.clone());
+        }
+        Self {
+            client: client
+                .build()
+                .expect("the Airtable HTTP client settings should be valid"),
+            base_url: config.airtable_base_url.trim_end_matches('/').to_string(),
+            base_id: config.airtable_base_id.clone(),
+            api_key: RwLock::new(config.airtable_api_key.as_str().into()),
+            previous_api_key: RwLock::new(None),
+            key_rotation_grace_period: Duration::from_secs(config.key_rotation_grace_period_secs),
+            api_version: config.airtable_api_version,
+        }
+    }
+
+    /// URL of a table of the configured base, or of a record when the path is `{table}/{record}`.
+    pub(crate) fn table_url(&self, path: &str) -> String {
+        format!(
+            "{}/{}/{}/{path}",
+            self.base_url, self.api_version, self.base_id
+        )
+    }
+
+    /// Request for the schema of the tables of the configured base. The Meta API may need a
+    /// token with the `schema.bases:read` scope, which can be passed instead of the API key.
+    pub(crate) fn tables_schema_request(
+        &self,
+        meta_api_key: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let request = self.client.get(format!(
+            "{}/{}/meta/bases/{}/tables",
+            self.base_url, self.api_version, self.base_id
+        ));
+        match meta_api_key {
+            Some(meta_api_key) => request.bearer_auth(meta_api_key),
+            None => request.bearer_auth(self.api_key()),
+        }
+    }
+
+    fn api_key(&self) -> Arc<str> {
+        self.api_key.read().unwrap().clone()
+    }
+
+    /// Uses the new API key for all the requests built from now on.
+    pub(crate) fn rotate_api_key(&self, api_key: &str) {
+        let previous_api_key =
+            std::mem::replace(&mut *self.api_key.write().unwrap(), api_key.into());
+        *self.previous_api_key.write().unwrap() = Some((previous_api_key, Instant::now()));
+    }
+
+    /// Copy of the request authenticated with the previous API key, if the request uses the
+    /// current key and the grace period of the last rotation is not over. The previous key is
+    /// dropped once the grace period is over.
+    pub(crate) fn with_previous_api_key(
+        &self,
+        request: &reqwest::Request,
+    ) -> Option<reqwest::Request> {
+        let previous_api_key = {
+            let mut previous_api_key = self.previous_api_key.write().unwrap();
+            match &*previous_api_key {
+                Some((api_key, rotated_at))
+                    if rotated_at.elapsed() < self.key_rotation_grace_period =>
+                {
+                    api_key.clone()
+                }
+                Some(_) => {
+                    *previous_api_key = None;
+                    return None;
+                }
+                None => return None,
+            }
+        };
+        let authorization = request.headers().get(reqwest::header::AUTHORIZATION)?;
+        if authorization.as_bytes() != format!("Bearer {}", self.api_key()).as_bytes() {
+            return None;
+        }
+        let mut request = request.try_clone()?;
+        let mut authorization =
+            reqwest::header::HeaderValue::from_str(&format!("Bearer {previous_api_key}")).ok()?;
+        authorization.set_sensitive(true);
+        request
+            .headers_mut()
+            .insert(reqwest::header::AUTHORIZATION, authorization);
+        Some(request)
+    }
+
+    /// Request to a table of the configured base, see [`AirtableHttpClient::table_url`].
+    pub(crate) fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        // Both API versions take personal access tokens and OAuth access tokens as bearer tokens
+        self.client
+            .request(method, self.table_url(path))
+            .bearer_auth(self.api_key())
+    }
+}
+
+#[axum::async_trait]
+impl AirtableProvider for AirtableHttpClient {
+    /// Sends the request through the circuit breaker of the state. Formulas too long for the URL
+    /// are sent in the body of a POST request instead.
+    async fn query_records(
+        &self,
+        state: &AppState,
+        query: &[(&str, &str)],
+    ) -> Result<serde_json::Value, KycError> {
+        let formula = query
+            .iter()
+            .find_map(|(key, value)| (*key == "filterByFormula").then_some(*value))
+            .unwrap_or_default();
+        let request = if crate::should_use_post_filter(formula, state.config.max_url_length) {
+            // The list records endpoint also takes the query parameters as a JSON body
+            let body = query
+                .iter()
+                .map(|&(key, value)| {
+                    let value = match value.parse::<u64>() {
+                        Ok(number) if key == "maxRecords" => number.into(),
+                        _ => value.into(),
+                    };
+                    (key.to_string(), value)
+                })
+                .collect::<serde_json::Map<_, _>>();
+            self.request(
+                reqwest::Method::POST,
+                &format!("{}/listRecords", crate::AIRTABLE_TABLE),
+            )
+            .json(&body)
+        } else {
+            self.request(reqwest::Method::GET, crate::AIRTABLE_TABLE)
+                .query(query)
+        };
+        crate::send_airtable_request(state, request)
+            .await?
+            .json()
+            .await
+            .map_err(|err| {
+                tracing::warn!(error = %err, "unexpected Airtable response");
+                KycError::DeserializationError
+            })
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    timeline::{truncate_ip, TimelineEvent},
+    AppState, KycStatus,
+};
+
+/// Tracing target of the audit events, so a subscriber can route them to a dedicated sink.
+pub(crate) const AUDIT_TARGET: &str = "kyc_audit";
+
+/// Response extension a handler attaches so the audit event records the looked up account and
+/// its KYC status.
+#[derive(Clone)]
+pub(crate) struct AuditedLookup {
+    pub account_id: near_account_id::AccountId,
+    pub kyc_status: KycStatus,
+}
+
+const MAX_DELETION_LOG_ENTRIES: usize = 10_000;
+
+/// A GDPR erasure of the data of an account.
+#[derive(Clone, serde::Serialize)]
+pub(crate) struct DeletionLogEntry {
+    pub account_id: near_account_id::AccountId,
+    /// Who handled the erasure request, as passed by the admin client.
+    pub operator: String,
+    /// The [`requester_network`], as the full address is not needed to trace the request.
+    pub requester_ip: Option<String>,
+    pub deleted_airtable_records: usize,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// The last [`MAX_DELETION_LOG_ENTRIES`] erasures, so operators can show an erasure request was
+/// handled after the account data is gone. Kept in memory, so it does not survive a restart, the
+/// audit events of the erasures do.
+#[derive(Default)]
+pub(crate) struct DeletionLog {
+    entries: Mutex<VecDeque<DeletionLogEntry>>,
+}
+
+impl DeletionLog {
+    pub(crate) fn record(&self, entry: DeletionLogEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == MAX_DELETION_LOG_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Erasures, oldest first.
+    pub(crate) fn entries(&self) -> Vec<DeletionLogEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Emits an audit event for every KYC request.
+pub(crate) async fn audit_kyc_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    if !(path.starts_with("/kyc/") || path.starts_with("/v1/kyc/")) {
+        return next.run(request).await;
+    }
+
+    let request_id = request
+        .headers()
+        .get("X-Request-Id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let requester_network = requester_network(request.headers(), &state.config.trusted_proxy_cidrs);
+
+    let response = next.run(request).await;
+
+    let lookup = response.extensions().get::<AuditedLookup>();
+    if let Some(lookup) = lookup {
+        state.timeline.record(
+            &lookup.account_id,
+            TimelineEvent::Lookup {
+                kyc_status: lookup.kyc_status,
+                requester_network: requester_network.clone(),
+            },
+        );
+    }
+    tracing::info!(
+        target: AUDIT_TARGET,
+        %request_id,
+        requester_ip = requester_network.as_deref().unwrap_or("unknown"),
+        %path,
+        account_id = lookup.map(|lookup| lookup.account_id.as_str()),
+        result_status = lookup.map(|lookup| tracing::field::debug(lookup.kyc_status)),
+        http_status = response.status().as_u16(),
+        timestamp = %chrono::Utc::now().to_rfc3339(),
+        "KYC request"
+    );
+    response
+}
+
+/// The service runs behind the Shuttle proxy, so the client address comes from `X-Forwarded-For`.
+/// Clients can send the header themselves, so only the addresses appended by the proxies are
+/// trusted: the client is the last address which is not one of the `trusted_proxies`, skipping
+/// the proxies in front of the Shuttle proxy (e.g. a load balancer) from the right.
+pub(crate) fn requester_ip(
+    headers: &axum::http::HeaderMap,
+    trusted_proxies: &[ipnet::IpNet],
+) -> Option<String> {
+    let forwarded_for = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .map(|value| value.to_str().ok())
+        .collect::<Option<Vec<_>>>()?
+        .join(",");
+    let mut addresses = forwarded_for
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .rev()
+        .peekable();
+    while let Some(address) = addresses.next() {
+        let is_trusted_proxy = address
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| trusted_proxies.iter().any(|proxy| proxy.contains(&ip)));
+        // When every address is a trusted proxy, the leftmost one is the closest to the client
+        if !is_trusted_proxy || addresses.peek().is_none() {
+            return Some(address.to_string());
+        }
+    }
+    None
+}
+
+/// Network of the [`requester_ip`], truncated with [`truncate_ip`] for data minimization. This is
+/// what gets logged and kept, while the full address is only used for rate limiting.
+pub(crate) fn requester_network(
+    headers: &axum::http::HeaderMap,
+    trusted_proxies: &[ipnet::IpNet],
+) -> Option<String> {
+    let ip = requester_ip(headers, trusted_proxies)?.parse().ok()?;
+    Some(truncate_ip(ip).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderMap;
+
+    use super::*;
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", value.parse().unwrap());
+        headers
+    }
+
+    fn trusted_proxies() -> Vec<ipnet::IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    #[test]
+    fn trusted_proxies_are_skipped() {
+        let headers = forwarded_for("203.0.113.7, 10.0.0.2, 10.0.0.1");
+        assert_eq!(
+            requester_ip(&headers, &trusted_proxies()).as_deref(),
+            Some("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn spoofed_addresses_are_ignored() {
+        // The client sent its own header, which the untrusted proxy appended its address to
+        let headers = forwarded_for("10.0.0.9, 198.51.100.4");
+        assert_eq!(
+            requester_ip(&headers, &trusted_proxies()).as_deref(),
+            Some("198.51.100.4")
+        );
+
+        let headers = forwarded_for("192.0.2.1, 203.0.113.7, 10.0.0.1");
+        assert_eq!(
+            requester_ip(&headers, &trusted_proxies()).as_deref(),
+            Some("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn requester_networks_are_truncated() {
+        let headers = forwarded_for("203.0.113.7, 10.0.0.1");
+        assert_eq!(
+            requester_network(&headers, &trusted_proxies()).as_deref(),
+            Some("203.0.113.0/24")
+        );
+        let headers = forwarded_for("2001:db8:1234:5678::1");
+        assert_eq!(
+            requester_network(&headers, &trusted_proxies()).as_deref(),
+            Some("2001:db8:1234::/48")
+        );
+        assert_eq!(
+            requester_network(&forwarded_for("unknown"), &trusted_proxies()),
+            None
+        );
+    }
+
+    #[test]
+    fn requests_without_header_have_no_requester() {
+        assert_eq!(requester_ip(&HeaderMap::new(), &trusted_proxies()), None);
+        assert_eq!(
+            requester_ip(&forwarded_for(" , "), &trusted_proxies()),
+            None
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use crate::KycStatus;
+
+const LABEL: &str = "KYC";
+const LABEL_WIDTH: usize = 34;
+/// Average width of the 11px Verdana characters, as the text is not measured.
+const CHARACTER_WIDTH: usize = 7;
+
+fn message_and_color(kyc_status: KycStatus) -> (&'static str, &'static str) {
+    match kyc_status {
+        KycStatus::Approved => ("APPROVED", "#4c1"),
+        KycStatus::Pending => ("PENDING", "#dfb317"),
+        KycStatus::Rejected => ("REJECTED", "#e05d44"),
+        KycStatus::Expired => ("EXPIRED", "#fe7d37"),
+        KycStatus::NotSubmitted => ("NOT SUBMITTED", "#9f9f9f"),
+    }
+}
+
+/// Renders a `[KYC | STATUS]` badge in the style of the shields.io flat badges.
+pub(crate) fn render_badge(kyc_status: KycStatus) -> String {
+    let (message, color) = message_and_color(kyc_status);
+    let message_width = message.len() * CHARACTER_WIDTH + 10;
+    let width = LABEL_WIDTH + message_width;
+    let label_x = LABEL_WIDTH / 2;
+    let message_x = LABEL_WIDTH + message_width / 2;
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{LABEL}: {message}">
+<title>{LABEL}: {message}</title>
+<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#r)"><rect width="{LABEL_WIDTH}" height="20" fill="#555"/><rect x="{LABEL_WIDTH}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+<text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{LABEL}</text><text x="{label_x}" y="14">{LABEL}</text>
+<text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text><text x="{message_x}" y="14">{message}</text>
+</g>
+</svg>"##
+    )
+}
+
+/// Entity tag of the badge, which only changes with the KYC status.
+pub(crate) fn badge_etag(badge: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, badge.as_bytes());
+    format!("\"{}\"", crate::signing::to_hex(&digest.as_ref()[..8]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge_shows_status_in_its_color() {
+        let badge = render_badge(KycStatus::Approved);
+        assert!(badge.starts_with("<svg "));
+        assert!(badge.contains(r#"aria-label="KYC: APPROVED""#));
+        assert!(badge.contains(r##"fill="#4c1""##));
+
+        let badge = render_badge(KycStatus::NotSubmitted);
+        assert!(badge.contains("<title>KYC: NOT SUBMITTED</title>"));
+        assert!(badge.contains(r##"fill="#9f9f9f""##));
+    }
+
+    #[test]
+    fn badge_width_fits_the_message() {
+        // 34 for the label, and 8 characters of 7px plus 10px of padding for the message
+        assert!(render_badge(KycStatus::Approved).contains(r#"width="100" height="20" role="img""#));
+    }
+
+    #[test]
+    fn etag_changes_with_the_status() {
+        let approved = badge_etag(&render_badge(KycStatus::Approved));
+        assert_eq!(approved, badge_etag(&render_badge(KycStatus::Approved)));
+        assert_ne!(approved, badge_etag(&render_badge(KycStatus::Rejected)));
+        assert_eq!(approved.len(), 18);
+        assert!(approved.starts_with('"') && approved.ends_with('"'));
+    }
+}
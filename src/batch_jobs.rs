@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::BatchItem;
+
+/// Finished jobs are kept around for this long, so clients have time to poll the results.
+const FINISHED_JOB_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BatchJobStatus {
+    Queued,
+    Processing,
+    Completed,
+    Failed,
+}
+
+#[derive(serde::Serialize)]
+pub(crate) struct BatchJobProgress {
+    done: usize,
+    total: usize,
+}
+
+#[derive(serde::Serialize)]
+pub(crate) struct BatchJob {
+    job_id: uuid::Uuid,
+    status: BatchJobStatus,
+    progress: BatchJobProgress,
+    /// Results in the request order, available once the job is completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<Vec<BatchItem>>,
+    #[serde(skip)]
+    finished_at: Option<Instant>,
+}
+
+/// State of the asynchronous batch jobs, kept in memory.
+#[derive(Default)]
+pub(crate) struct BatchJobs {
+    jobs: Mutex<HashMap<uuid::Uuid, BatchJob>>,
+}
+
+impl BatchJobs {
+    pub(crate) fn create(&self, total: usize) -> uuid::Uuid {
+        let job_id = uuid::Uuid::new_v4();
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|finished_at| finished_at.elapsed() < FINISHED_JOB_RETENTION)
+        });
+        jobs.insert(
+            job_id,
+            BatchJob {
+                job_id,
+                status: BatchJobStatus::Queued,
+                progress: BatchJobProgress { done: 0, total },
+                results: None,
+                finished_at: None,
+            },
+        );
+        job_id
+    }
+
+    fn update(&self, job_id: uuid::Uuid, update: impl FnOnce(&mut BatchJob)) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&job_id) {
+            update(job);
+        }
+    }
+
+    pub(crate) fn start(&self, job_id: uuid::Uuid) {
+        self.update(job_id, |job| job.status = BatchJobStatus::Processing);
+    }
+
+    pub(crate) fn record_progress(&self, job_id: uuid::Uuid) {
+        self.update(job_id, |job| job.progress.done += 1);
+    }
+
+    pub(crate) fn complete(&self, job_id: uuid::Uuid, results: Vec<BatchItem>) {
+        self.update(job_id, |job| {
+            job.status = BatchJobStatus::Completed;
+            job.results = Some(results);
+            job.finished_at = Some(Instant::now());
+        });
+    }
+
+    pub(crate) fn fail(&self, job_id: uuid::Uuid) {
+        self.update(job_id, |job| {
+            job.status = BatchJobStatus::Failed;
+            job.finished_at = Some(Instant::now());
+        });
+    }
+
+    pub(crate) fn to_json(&self, job_id: uuid::Uuid) -> Option<serde_json::Value> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(&job_id)
+            .map(|job| serde_json::to_value(job).expect("batch jobs are serializable"))
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    encryption::{EncryptedBytes, FieldEncryptor},
+    wallet::WalletAddress,
+    KycStatus, Leaderboard,
+};
+
+const MAX_LOOKUP_SAMPLES: usize = 1000;
+
+type Entries = HashMap<(WalletAddress, String), (KycStatus, Instant)>;
+
+/// A cached status, as reported to the account holder.
+#[derive(serde::Serialize)]
+pub(crate) struct CacheEntry {
+    view: String,
+    kyc_status: KycStatus,
+    age_secs: u64,
+}
+
+/// A cached status as written to the `KYC_CACHE_PERSIST_PATH`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PersistedEntry {
+    #[serde(flatten)]
+    status: PersistedStatus,
+    view: String,
+    cached_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The account and its status, which are personal data together, so they are encrypted when a
+/// `FIELD_ENCRYPTION_KEY` is set.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum PersistedStatus {
+    Plain {
+        account_id: near_account_id::AccountId,
+        kyc_status: KycStatus,
+    },
+    Encrypted {
+        /// `[account_id, kyc_status]`
+        encrypted: EncryptedBytes,
+    },
+}
+
+fn wallet_entries(entries: &Entries, wallet_address: &WalletAddress) -> Vec<CacheEntry> {
+    entries
+        .iter()
+        .filter(|((cached_wallet_address, _), _)| cached_wallet_address == wallet_address)
+        .map(|((_, view), (kyc_status, cached_at))| CacheEntry {
+            view: view.clone(),
+            kyc_status: *kyc_status,
+            age_secs: cached_at.elapsed().as_secs(),
+        })
+        .collect()
+}
+
+/// Looked up KYC statuses by wallet address and Airtable view, kept for the configured TTL.
+pub(crate) struct KycCache {
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    /// When the last [`MAX_LOOKUP_SAMPLES`] cache lookups happened and whether they were hits.
+    lookups: Mutex<VecDeque<(Instant, bool)>>,
+}
+
+impl KycCache {
+    /// A zero TTL disables the cache.
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+            lookups: Default::default(),
+        }
+    }
+
+    pub(crate) fn get(&self, wallet_address: &WalletAddress, view: &str) -> Option<KycStatus> {
+        let kyc_status = self.get_entry(wallet_address, view);
+        let mut lookups = self.lookups.lock().unwrap();
+        if lookups.len() == MAX_LOOKUP_SAMPLES {
+            lookups.pop_front();
+        }
+        lookups.push_back((Instant::now(), kyc_status.is_some()));
+        kyc_status
+    }
+
+    fn get_entry(&self, wallet_address: &WalletAddress, view: &str) -> Option<KycStatus> {
+        let mut entries = self.entries.lock().unwrap();
+        let key = (wallet_address.clone(), view.to_string());
+        match entries.get(&key) {
+            Some((kyc_status, cached_at)) if cached_at.elapsed() < self.ttl => Some(*kyc_status),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, wallet_address: &WalletAddress, view: &str, kyc_status: KycStatus) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let ttl = self.ttl;
+        entries.retain(|_, (_, cached_at)| cached_at.elapsed() < ttl);
+        entries.insert(
+            (wallet_address.clone(), view.to_string()),
+            (kyc_status, Instant::now()),
+        );
+    }
+
+    /// Serializes the statuses of the NEAR accounts which have not expired yet. The other wallet
+    /// addresses are rare enough to be looked up again.
+    pub(crate) fn to_json(&self, encryptor: Option<&FieldEncryptor>) -> Vec<u8> {
+        let now = chrono::Utc::now();
+        let entries = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (_, cached_at))| cached_at.elapsed() < self.ttl)
+            .filter_map(|((wallet_address, view), (kyc_status, cached_at))| {
+                let WalletAddress::Near(account_id) = wallet_address else {
+                    return None;
+                };
+                let status = match encryptor {
+                    Some(encryptor) => PersistedStatus::Encrypted {
+                        encrypted: encryptor.encrypt(&(account_id, kyc_status)),
+                    },
+                    None => PersistedStatus::Plain {
+                        account_id: account_id.clone(),
+                        kyc_status: *kyc_status,
+                    },
+                };
+                Some(PersistedEntry {
+                    status,
+                    view: view.clone(),
+                    cached_at: now - cached_at.elapsed(),
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_vec(&entries).expect("the cache entries are serializable")
+    }
+
+    /// Adds the statuses serialized with [`KycCache::to_json`], skipping the ones whose TTL ran
+    /// out in the meantime and the encrypted ones which cannot be decrypted with the current key.
+    /// Returns how many were added.
+    pub(crate) fn load_json(
+        &self,
+        json: &[u8],
+        encryptor: Option<&FieldEncryptor>,
+    ) -> Result<usize, serde_json::Error> {
+        let persisted_entries: Vec<PersistedEntry> = serde_json::from_slice(json)?;
+        let now = chrono::Utc::now();
+        let mut entries = self.entries.lock().unwrap();
+        let mut loaded = 0;
+        for entry in persisted_entries {
+            let Some(cached_at) = (now - entry.cached_at)
+                .to_std()
+                .ok()
+                .filter(|age| *age < self.ttl)
+                .and_then(|age| Instant::now().checked_sub(age))
+            else {
+                continue;
+            };
+            let (account_id, kyc_status) = match entry.status {
+                PersistedStatus::Plain {
+                    account_id,
+                    kyc_status,
+                } => (account_id, kyc_status),
+                PersistedStatus::Encrypted { encrypted } => {
+                    match encryptor.map(|encryptor| encryptor.decrypt(&encrypted)) {
+                        Some(Ok(status)) => status,
+                        _ => continue,
+                    }
+                }
+            };
+            entries.insert(
+                (WalletAddress::Near(account_id), entry.view),
+                (kyc_status, cached_at),
+            );
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Number of cached statuses, including the expired ones which were not cleaned up yet.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Share of the lookups within the `window` which were served from the cache.
+    pub(crate) fn hit_rate(&self, window: Duration) -> Option<f64> {
+        let lookups = self.lookups.lock().unwrap();
+        let (hits, total) = lookups
+            .iter()
+            .filter(|(looked_up_at, _)| looked_up_at.elapsed() <= window)
+            .fold((0, 0), |(hits, total), (_, hit)| {
+                (hits + usize::from(*hit), total + 1)
+            });
+        (total > 0).then(|| hits as f64 / total as f64)
+    }
+
+    /// Cached statuses of the wallet in all the views, including the expired ones which were not
+    /// cleaned up yet.
+    pub(crate) fn entries(&self, wallet_address: &WalletAddress) -> Vec<CacheEntry> {
+        wallet_entries(&self.entries.lock().unwrap(), wallet_address)
+    }
+
+    /// Removes the cached statuses of the wallet in all the views.
+    pub(crate) fn evict(&self, wallet_address: &WalletAddress) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(cached_wallet_address, _), _| cached_wallet_address != wallet_address);
+    }
+}
+
+/// Last successfully looked up KYC statuses, kept without a TTL to be served when Airtable returns
+/// unusable responses (e.g. after a schema change). Once `max_entries` statuses are kept, the least
+/// recently looked up one is dropped for every new one.
+pub(crate) struct StaleKycCache {
+    max_entries: usize,
+    entries: Mutex<Entries>,
+}
+
+impl StaleKycCache {
+    pub(crate) fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Default::default(),
+        }
+    }
+
+    /// Returns the status and when it was looked up.
+    pub(crate) fn get(
+        &self,
+        wallet_address: &WalletAddress,
+        view: &str,
+    ) -> Option<(KycStatus, Instant)> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&(wallet_address.clone(), view.to_string()))
+            .copied()
+    }
+
+    pub(crate) fn insert(&self, wallet_address: &WalletAddress, view: &str, kyc_status: KycStatus) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let key = (wallet_address.clone(), view.to_string());
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let least_recent_key = entries
+                .iter()
+                .min_by_key(|(_, (_, looked_up_at))| *looked_up_at)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recent_key) = least_recent_key {
+                entries.remove(&least_recent_key);
+            }
+        }
+        entries.insert(key, (kyc_status, Instant::now()));
+    }
+
+    /// Removes the status of the wallet in the view.
+    pub(crate) fn remove(&self, wallet_address: &WalletAddress, view: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&(wallet_address.clone(), view.to_string()));
+    }
+
+    pub(crate) fn entries(&self, wallet_address: &WalletAddress) -> Vec<CacheEntry> {
+        wallet_entries(&self.entries.lock().unwrap(), wallet_address)
+    }
+
+    pub(crate) fn evict(&self, wallet_address: &WalletAddress) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(cached_wallet_address, _), _| cached_wallet_address != wallet_address);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+/// Leaderboards by cohort, kept for the configured TTL as they take a lookup per cohort account.
+pub(crate) struct LeaderboardCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Leaderboard, Instant)>>,
+}
+
+impl LeaderboardCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    pub(crate) fn get(&self, cohort: &str) -> Option<Leaderboard> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(cohort)
+            .filter(|(_, cached_at)| cached_at.elapsed() < self.ttl)
+            .map(|(leaderboard, _)| leaderboard.clone())
+    }
+
+    pub(crate) fn insert(&self, leaderboard: Leaderboard) {
+        let mut entries = self.entries.lock().unwrap();
+        let ttl = self.ttl;
+        entries.retain(|_, (_, cached_at)| cached_at.elapsed() < ttl);
+        entries.insert(leaderboard.cohort.clone(), (leaderboard, Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alice() -> WalletAddress {
+        WalletAddress::Near("alice.near".parse().unwrap())
+    }
+
+    #[test]
+    fn statuses_are_cached_by_view() {
+        let cache = KycCache::new(Duration::from_secs(60));
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        assert_eq!(cache.get(&alice(), "Grid view"), Some(KycStatus::Approved));
+        assert_eq!(cache.get(&alice(), "Other view"), None);
+        assert_eq!(cache.hit_rate(Duration::from_secs(60)), Some(0.5));
+    }
+
+    #[test]
+    fn expired_statuses_are_dropped() {
+        let cache = KycCache::new(Duration::from_millis(20));
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(&alice(), "Grid view"), None);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn zero_ttl_disables_the_cache() {
+        let cache = KycCache::new(Duration::ZERO);
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        assert_eq!(cache.get(&alice(), "Grid view"), None);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn evict_removes_all_views() {
+        let cache = KycCache::new(Duration::from_secs(60));
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        cache.insert(&alice(), "Other view", KycStatus::Pending);
+        cache.evict(&alice());
+        assert!(cache.entries(&alice()).is_empty());
+    }
+
+    #[test]
+    fn stale_cache_drops_least_recently_looked_up_status() {
+        let bob = WalletAddress::Near("bob.near".parse().unwrap());
+        let carol = WalletAddress::Near("carol.near".parse().unwrap());
+        let cache = StaleKycCache::new(2);
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert(&bob, "Grid view", KycStatus::Pending);
+        std::thread::sleep(Duration::from_millis(2));
+        // Looking up a kept status again makes it the most recent one
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert(&carol, "Grid view", KycStatus::Rejected);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&alice(), "Grid view").is_some());
+        assert!(cache.get(&bob, "Grid view").is_none());
+        assert!(cache.get(&carol, "Grid view").is_some());
+    }
+
+    #[test]
+    fn persisted_statuses_round_trip() {
+        let cache = KycCache::new(Duration::from_secs(60));
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        let json = cache.to_json(None);
+
+        let restored = KycCache::new(Duration::from_secs(60));
+        assert_eq!(restored.load_json(&json, None).unwrap(), 1);
+        assert_eq!(
+            restored.get(&alice(), "Grid view"),
+            Some(KycStatus::Approved)
+        );
+    }
+
+    #[test]
+    fn persisted_statuses_are_encrypted_with_the_key() {
+        let encryptor = FieldEncryptor::new(&[1; 32]);
+        let cache = KycCache::new(Duration::from_secs(60));
+        cache.insert(&alice(), "Grid view", KycStatus::Approved);
+        let json = cache.to_json(Some(&encryptor));
+        let json_text = String::from_utf8(json.clone()).unwrap();
+        assert!(!json_text.contains("alice.near"));
+        assert!(!json_text.contains("APPROVED"));
+
+        let restored = KycCache::new(Duration::from_secs(60));
+        assert_eq!(restored.load_json(&json, Some(&encryptor)).unwrap(), 1);
+        assert_eq!(
+            restored.get(&alice(), "Grid view"),
+            Some(KycStatus::Approved)
+        );
+
+        let other_encryptor = FieldEncryptor::new(&[2; 32]);
+        let restored = KycCache::new(Duration::from_secs(60));
+        assert_eq!(
+            restored.load_json(&json, Some(&other_encryptor)).unwrap(),
+            0
+        );
+        assert_eq!(restored.load_json(&json, None).unwrap(), 0);
+    }
+
+    #[test]
+    fn persisted_statuses_past_their_ttl_are_not_loaded() {
+        let cached_at = |age| chrono::Utc::now() - chrono::Duration::seconds(age);
+        let json = serde_json::to_vec(&serde_json::json!([
+            {"account_id": "alice.near", "view": "Grid view", "kyc_status": "APPROVED", "cached_at": cached_at(30)},
+            {"account_id": "bob.near", "view": "Grid view", "kyc_status": "APPROVED", "cached_at": cached_at(90)},
+        ]))
+        .unwrap();
+
+        let cache = KycCache::new(Duration::from_secs(60));
+        assert_eq!(cache.load_json(&json, None).unwrap(), 1);
+        assert_eq!(cache.get(&alice(), "Grid view"), Some(KycStatus::Approved));
+        let bob = WalletAddress::Near("bob.near".parse().unwrap());
+        assert_eq!(cache.get(&bob, "Grid view"), None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::AppState;
+
+/// Fills the KYC cache from the file saved by the previous instance, if any.
+pub(crate) fn load_kyc_cache(state: &AppState, path: &Path) {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return,
+        Err(err) => {
+            tracing::warn!(path = %path.display(), "failed to read the persisted KYC cache: {err}");
+            return;
+        }
+    };
+    match state
+        .kyc_cache
+        .load_json(&json, state.field_encryptor.as_ref())
+    {
+        Ok(loaded) => {
+            tracing::info!(path = %path.display(), loaded, "Persisted KYC cache was loaded");
+        }
+        Err(err) => {
+            tracing::warn!(path = %path.display(), "failed to parse the persisted KYC cache: {err}");
+        }
+    }
+}
+
+/// Saves the KYC cache every `KYC_CACHE_PERSIST_INTERVAL_SECS`, so the file is at most that old
+/// when the service is stopped.
+pub(crate) async fn run_kyc_cache_persistence(state: Arc<AppState>, path: PathBuf) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        state.config.kyc_cache_persist_interval_secs,
+    ));
+    // The first tick completes right away, when there is nothing new to save
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if let Err(err) = save_kyc_cache(&state, &path).await {
+            tracing::warn!(path = %path.display(), "failed to persist the KYC cache: {err}");
+        }
+    }
+}
+
+async fn save_kyc_cache(state: &AppState, path: &Path) -> std::io::Result<()> {
+    write_file_atomically(
+        path,
+        state.kyc_cache.to_json(state.field_encryptor.as_ref()),
+    )
+    .await?;
+    tracing::debug!(path = %path.display(), "KYC cache was persisted");
+    Ok(())
+}
+
+/// Writes to a temporary file first, so a stop in the middle of the write does not leave a
+/// truncated file behind.
+pub(crate) async fn write_file_atomically(path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    tokio::fs::write(&temporary_path, contents).await?;
+    tokio::fs::rename(&temporary_path, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, wallet::WalletAddress, KycStatus};
+
+    #[tokio::test]
+    async fn saved_cache_is_loaded_by_the_next_instance() {
+        let path = std::env::temp_dir().join(format!("kyc_cache_{}.json", uuid::Uuid::new_v4()));
+        let alice = WalletAddress::Near("alice.near".parse().unwrap());
+        let config = || Config {
+            kyc_cache_ttl_secs: 60,
+            ..Default::default()
+        };
+
+        let state = AppState::builder().with_config(config()).build();
+        state
+            .kyc_cache
+            .insert(&alice, "Grid view", KycStatus::Approved);
+        save_kyc_cache(&state, &path).await.unwrap();
+
+        let restarted_state = AppState::builder().with_config(config()).build();
+        load_kyc_cache(&restarted_state, &path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            restarted_state.kyc_cache.get(&alice, "Grid view"),
+            Some(KycStatus::Approved)
+        );
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_cache() {
+        let state = AppState::builder().build();
+        load_kyc_cache(&state, Path::new("/nonexistent/kyc_cache.json"));
+        assert_eq!(state.kyc_cache.len(), 0);
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CircuitState {
+    /// Requests go through.
+    Closed,
+    /// Too many consecutive failures, requests are rejected without calling the upstream.
+    Open,
+    /// The open period is over, requests are let through to probe whether the upstream recovered.
+    HalfOpen,
+}
+
+/// Stops calling Airtable for a while after it fails `failure_threshold` times in a row.
+pub(crate) struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    inner: Mutex<CircuitBreakerInner>,
+}
+
+#[derive(Default)]
+struct CircuitBreakerInner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// Keeps the circuit open regardless of the open duration until it is released.
+    held_open: bool,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            failure_threshold,
+            open_duration,
+            inner: Default::default(),
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        let inner = self.inner.lock().unwrap();
+        if inner.held_open {
+            return CircuitState::Open;
+        }
+        match inner.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.open_duration => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    pub(crate) fn allows_requests(&self) -> bool {
+        self.state() != CircuitState::Open
+    }
+
+    pub(crate) fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner = CircuitBreakerInner {
+            held_open: inner.held_open,
+            ..Default::default()
+        };
+    }
+
+    /// Opens the circuit until [`CircuitBreaker::release`] is called.
+    pub(crate) fn hold_open(&self) {
+        self.inner.lock().unwrap().held_open = true;
+    }
+
+    pub(crate) fn is_held_open(&self) -> bool {
+        self.inner.lock().unwrap().held_open
+    }
+
+    pub(crate) fn release(&self) {
+        self.inner.lock().unwrap().held_open = false;
+    }
+
+    pub(crate) fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        // A failed probe in the half-open state opens the circuit again right away
+        if inner.consecutive_failures >= self.failure_threshold || inner.opened_at.is_some() {
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// Circuit of the individual lookups, which is also the default one.
+pub(crate) const LOOKUP_CIRCUIT: &str = "lookup";
+/// Circuit of the batch lookups and the background tasks, whose error rates should not block the
+/// individual lookups.
+pub(crate) const BATCH_CIRCUIT: &str = "batch";
+
+tokio::task_local! {
+    static CURRENT_CIRCUIT: &'static str;
+}
+
+/// Runs the future with its Airtable requests going through the [`BATCH_CIRCUIT`]. Tasks spawned
+/// by the future are not covered.
+pub(crate) async fn in_batch_circuit<F: std::future::Future>(future: F) -> F::Output {
+    CURRENT_CIRCUIT.scope(BATCH_CIRCUIT, future).await
+}
+
+/// Circuit the Airtable requests of the current task go through.
+pub(crate) fn current_circuit() -> &'static str {
+    CURRENT_CIRCUIT
+        .try_with(|circuit| *circuit)
+        .unwrap_or(LOOKUP_CIRCUIT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_opens_after_consecutive_failures() {
+        let circuit_breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        circuit_breaker.record_failure();
+        circuit_breaker.record_failure();
+        circuit_breaker.record_success();
+        circuit_breaker.record_failure();
+        circuit_breaker.record_failure();
+        assert!(circuit_breaker.state() == CircuitState::Closed);
+        circuit_breaker.record_failure();
+        assert!(circuit_breaker.state() == CircuitState::Open);
+        assert!(!circuit_breaker.allows_requests());
+    }
+
+    #[test]
+    fn failed_probe_opens_the_circuit_again() {
+        let circuit_breaker = CircuitBreaker::new(1, Duration::ZERO);
+        circuit_breaker.record_failure();
+        assert!(circuit_breaker.state() == CircuitState::HalfOpen);
+        assert!(circuit_breaker.allows_requests());
+        circuit_breaker.record_success();
+        assert!(circuit_breaker.state() == CircuitState::Closed);
+    }
+
+    #[test]
+    fn held_open_circuit_ignores_successes_until_released() {
+        let circuit_breaker = CircuitBreaker::new(3, Duration::ZERO);
+        circuit_breaker.hold_open();
+        circuit_breaker.record_success();
+        assert!(circuit_breaker.state() == CircuitState::Open);
+        circuit_breaker.release();
+        assert!(circuit_breaker.state() == CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn batch_futures_use_the_batch_circuit() {
+        assert_eq!(current_circuit(), LOOKUP_CIRCUIT);
+        assert_eq!(
+            in_batch_circuit(async { current_circuit() }).await,
+            BATCH_CIRCUIT
+        );
+        // Spawned tasks are not covered
+        let spawned = in_batch_circuit(async { tokio::spawn(async { current_circuit() }).await })
+            .await
+            .unwrap();
+        assert_eq!(spawned, LOOKUP_CIRCUIT);
+        assert_eq!(current_circuit(), LOOKUP_CIRCUIT);
+    }
+}
@@ -0,0 +1,967 @@
+use anyhow::anyhow;
+use axum::http::HeaderValue;
+use shuttle_runtime::SecretStore;
+
+const DEFAULT_AIRTABLE_BASE_URL: &str = "https://api.airtable.com";
+const MAINNET_AIRTABLE_BASE_ID: &str = "appjaTXAImNymlY6T";
+const DEFAULT_AIRTABLE_VIEW_NAME: &str = "Grid view";
+const DEFAULT_AIRTABLE_MAX_RECORDS: u32 = 100;
+const MAX_AIRTABLE_MAX_RECORDS: u32 = 1000;
+const DEFAULT_RETRY_INITIAL_MS: u64 = 400;
+const DEFAULT_RETRY_MULTIPLIER: f64 = 2.0;
+const DEFAULT_RETRY_MAX_MS: u64 = 10_000;
+const DEFAULT_RETRY_DELAY_MS: u64 = 200;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NearNetwork {
+    Mainnet,
+    Testnet,
+}
+
+impl std::fmt::Display for NearNetwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Mainnet => "mainnet",
+            Self::Testnet => "testnet",
+        })
+    }
+}
+
+impl NearNetwork {
+    fn default_rpc_url(self) -> &'static str {
+        match self {
+            Self::Mainnet => "https://rpc.mainnet.near.org",
+            Self::Testnet => "https://rpc.testnet.near.org",
+        }
+    }
+}
+
+impl std::str::FromStr for NearNetwork {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mainnet" => Ok(Self::Mainnet),
+            "testnet" => Ok(Self::Testnet),
+            _ => Err(anyhow!("unknown NEAR network")),
+        }
+    }
+}
+
+/// How the KYC status is picked when an account has multiple Airtable records.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum TieBreakingPolicy {
+    /// An active approval wins, otherwise the most recently created record is used.
+    PreferVerified,
+    /// The most recently created record is used.
+    MostRecent,
+    /// The most restrictive status is used.
+    Strictest,
+    /// The most permissive status is used.
+    Lenient,
+}
+
+impl std::str::FromStr for TieBreakingPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "PreferVerified" => Ok(Self::PreferVerified),
+            "MostRecent" => Ok(Self::MostRecent),
+            "Strictest" => Ok(Self::Strictest),
+            "Lenient" => Ok(Self::Lenient),
+            _ => Err(anyhow!("unknown tie-breaking policy")),
+        }
+    }
+}
+
+/// How the overall status of `?all_chains=true` lookups is picked out of the chains the account has
+/// records on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum AllChainsPolicy {
+    MostPermissive,
+    MostRestrictive,
+}
+
+impl std::str::FromStr for AllChainsPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "MostPermissive" => Ok(Self::MostPermissive),
+            "MostRestrictive" => Ok(Self::MostRestrictive),
+            _ => Err(anyhow!("unknown all chains policy")),
+        }
+    }
+}
+
+/// Version of the Airtable REST API the requests are sent to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum AirtableApiVersion {
+    V0,
+    V0_1,
+}
+
+impl std::fmt::Display for AirtableApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::V0 => "v0",
+            Self::V0_1 => "v0.1",
+        })
+    }
+}
+
+impl std::str::FromStr for AirtableApiVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "v0" => Ok(Self::V0),
+            "v0.1" => Ok(Self::V0_1),
+            _ => Err(anyhow!("unknown Airtable API version")),
+        }
+    }
+}
+
+/// Format of the KYC status responses.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum ResponseFormat {
+    Json,
+    /// JSON with the links to the related endpoints, see [`crate::hal::HalResponse`].
+    Hal,
+}
+
+impl std::str::FromStr for ResponseFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(Self::Json),
+            "hal" => Ok(Self::Hal),
+            _ => Err(anyhow!("unknown response format")),
+        }
+    }
+}
+
+/// Backoff between the retries of the Airtable calls.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum RetryStrategy {
+    /// `delay_ms` more for every attempt.
+    Linear {
+        delay_ms: u64,
+    },
+    Exponential {
+        initial_ms: u64,
+        multiplier: f64,
+        max_ms: u64,
+    },
+    Fixed {
+        delay_ms: u64,
+    },
+}
+
+impl RetryStrategy {
+    /// Delay before retrying the failed `attempt`, counted from 1.
+    pub(crate) fn next_delay(&self, attempt: u32) -> std::time::Duration {
+        let delay_ms = match *self {
+            Self::Linear { delay_ms } => delay_ms.saturating_mul(attempt.into()),
+            Self::Exponential {
+                initial_ms,
+                multiplier,
+                max_ms,
+            } => (initial_ms as f64 * multiplier.powi(attempt.saturating_sub(1) as i32))
+                .min(max_ms as f64) as u64,
+            Self::Fixed { delay_ms } => delay_ms,
+        };
+        std::time::Duration::from_millis(delay_ms)
+    }
+}
+
+/// Format of the logs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum LogFormat {
+    /// The default Shuttle text format.
+    Plain,
+    /// JSON in the Elastic Common Schema, see [`crate::ecs_log::EcsFormat`].
+    Ecs,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "plain" => Ok(Self::Plain),
+            "ecs" => Ok(Self::Ecs),
+            _ => Err(anyhow!("unknown log format")),
+        }
+    }
+}
+
+/// Deployment environment, which gates the testing aids.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Environment {
+    Production,
+    Development,
+    Test,
+}
+
+impl std::str::FromStr for Environment {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "production" => Ok(Self::Production),
+            "development" => Ok(Self::Development),
+            "test" => Ok(Self::Test),
+            _ => Err(anyhow!("unknown environment")),
+        }
+    }
+}
+
+/// Simulated failures for load and chaos testing, only allowed outside of production.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct ChaosConfig {
+    pub latency_ms: u64,
+    /// Share of the requests answered with a 500, between 0 and 1.
+    pub error_rate: f64,
+}
+
+/// Service configuration read from the Shuttle secrets on startup.
+pub(crate) struct Config {
+    pub airtable_api_key: String,
+    /// How long the previous API key is still tried after a rotation.
+    pub key_rotation_grace_period_secs: u64,
+    /// Token for the Airtable Meta API, when the data API token lacks the schema scope.
+    pub airtable_meta_api_key: Option<String>,
+    pub airtable_base_url: String,
+    pub airtable_api_version: AirtableApiVersion,
+    /// Airtable base holding the KYC records of the configured NEAR network.
+    pub airtable_base_id: String,
+    pub airtable_max_records: u32,
+    pub airtable_view_name: String,
+    /// Views with the same records as the default view, which the lookups rotate through.
+    pub airtable_view_pool: Vec<String>,
+    /// Views which can be requested instead of the default one.
+    pub airtable_allowed_views: Vec<String>,
+    /// Airtable formula matching the records of `{account_id}`, replacing the default formula.
+    pub airtable_filter_template: Option<String>,
+    pub tie_breaking_policy: TieBreakingPolicy,
+    pub all_chains_policy: AllChainsPolicy,
+    pub airtable_follow_linked_records: bool,
+    pub airtable_max_concurrent_requests: usize,
+    /// Total time the retries of an Airtable call may take, including the first attempt.
+    pub airtable_retry_budget_ms: u64,
+    pub airtable_retry_strategy: RetryStrategy,
+    /// Longest encoded filter formula sent in the query string, longer formulas are sent in the
+    /// body of a POST request.
+    pub max_url_length: usize,
+    pub max_request_body_bytes: usize,
+    /// Fill in the missing approval dates of the approved records when they are looked up.
+    pub airtable_writeback: bool,
+    /// Delete the Airtable records of an account on a GDPR erasure request.
+    pub gdpr_delete_from_airtable: bool,
+    /// Airtable API calls allowed per month by the Airtable plan.
+    pub airtable_monthly_quota: u64,
+    pub circuit_breaker_failure_threshold: u32,
+    pub circuit_breaker_open_secs: u64,
+    pub kyc_cache_ttl_secs: u64,
+    /// How many last known statuses are kept for when Airtable returns unusable responses.
+    pub stale_kyc_cache_max_entries: usize,
+    /// File the KYC cache is saved to every `kyc_cache_persist_interval_secs` and loaded from on
+    /// startup.
+    pub kyc_cache_persist_path: Option<std::path::PathBuf>,
+    pub kyc_cache_persist_interval_secs: u64,
+    /// File the ownership nonces are saved to whenever they are issued or used, and loaded from on
+    /// startup.
+    pub ownership_nonce_persist_path: Option<std::path::PathBuf>,
+    /// AES-256-GCM key the personal data in the `kyc_cache_persist_path` and the
+    /// `ownership_nonce_persist_path` is encrypted with.
+    pub field_encryption_key: Option<[u8; 32]>,
+    /// Send `Cache-Control` headers meant for reverse proxies and CDNs.
+    pub enable_http_cache: bool,
+    pub leaderboard_cache_ttl_secs: u64,
+    pub response_timeout_ms: u64,
+    pub response_format: ResponseFormat,
+    /// Share of the requests which are logged, between 0 and 1.
+    pub log_sample_rate: f64,
+    pub log_format: LogFormat,
+    pub environment: Environment,
+    pub chaos: Option<ChaosConfig>,
+    pub registration_token: Option<String>,
+    /// Enables the per-account KYC portal links.
+    pub registration_link: Option<crate::registration_link::RegistrationLinkConfig>,
+    pub registration_link_ttl_secs: i64,
+    pub airtable_webhook_token: Option<String>,
+    /// Secret the admin requests are signed with.
+    pub admin_secret: Option<String>,
+    /// Keys accepted in the `X-API-Key` header of the `/v1` lookups, which are open when empty.
+    pub api_keys: Vec<String>,
+    pub webhook_urls: Vec<String>,
+    /// Notified when the KYC table no longer has a column the proxy reads.
+    pub alert_webhook_url: Option<String>,
+    /// Account periodically looked up to check Airtable returns the expected status.
+    pub health_check_account_id: Option<near_account_id::AccountId>,
+    pub health_check_interval_secs: u64,
+    pub health_check_expected_status: crate::KycStatus,
+    /// Set the expired records back to pending when they are looked up.
+    pub auto_resubmit_on_expiry: bool,
+    pub kyc_expiry_days: i64,
+    pub expiry_warn_days: i64,
+    pub cors_allowed_origins: Vec<HeaderValue>,
+    /// Proxies in front of the service whose `X-Forwarded-For` entries are skipped to find the
+    /// client address.
+    pub trusted_proxy_cidrs: Vec<ipnet::IpNet>,
+    /// Outbound proxy the Airtable requests go through.
+    pub airtable_proxy: Option<reqwest::Proxy>,
+    /// Requests with a matching `User-Agent` are rejected.
+    pub blocked_user_agents: Option<regex::RegexSet>,
+    /// Airtable fields without which the records are skipped by the lookups.
+    pub required_airtable_fields: Vec<String>,
+    /// Test accounts which always have the configured status, without looking them up.
+    pub kyc_bypass_accounts:
+        std::collections::HashMap<near_account_id::AccountId, crate::KycStatus>,
+    /// JSON file of pre-exported statuses served while Airtable is unavailable.
+    pub fallback_static_file: Option<std::path::PathBuf>,
+    pub fallback_file_reload_secs: u64,
+    pub near_network: NearNetwork,
+    pub near_rpc_url: String,
+    /// Reject lookups without a transaction proof of the account ownership.
+    pub require_tx_proof: bool,
+}
+
+impl Default for Config {
+    /// Defaults of the optional secrets; the Airtable API key is left empty.
+    fn default() -> Self {
+        Self {
+            airtable_api_key: String::new(),
+            key_rotation_grace_period_secs: 60,
+            airtable_meta_api_key: None,
+            airtable_base_url: DEFAULT_AIRTABLE_BASE_URL.to_string(),
+            airtable_api_version: AirtableApiVersion::V0,
+            airtable_base_id: MAINNET_AIRTABLE_BASE_ID.to_string(),
+            airtable_max_records: DEFAULT_AIRTABLE_MAX_RECORDS,
+            airtable_view_name: DEFAULT_AIRTABLE_VIEW_NAME.to_string(),
+            airtable_view_pool: Vec::new(),
+            airtable_allowed_views: Vec::new(),
+            airtable_filter_template: None,
+            tie_breaking_policy: TieBreakingPolicy::PreferVerified,
+            all_chains_policy: AllChainsPolicy::MostPermissive,
+            airtable_follow_linked_records: false,
+            airtable_max_concurrent_requests: 5,
+            airtable_retry_budget_ms: 3000,
+            airtable_retry_strategy: RetryStrategy::Exponential {
+                initial_ms: DEFAULT_RETRY_INITIAL_MS,
+                multiplier: DEFAULT_RETRY_MULTIPLIER,
+                max_ms: DEFAULT_RETRY_MAX_MS,
+            },
+            max_url_length: 1800,
+            max_request_body_bytes: 1024 * 1024,
+            airtable_writeback: false,
+            gdpr_delete_from_airtable: false,
+            airtable_monthly_quota: u64::MAX,
+            circuit_breaker_failure_threshold: 5,
+            circuit_breaker_open_secs: 30,
+            kyc_cache_ttl_secs: 60,
+            stale_kyc_cache_max_entries: 10_000,
+            kyc_cache_persist_path: None,
+            kyc_cache_persist_interval_secs: 60,
+            ownership_nonce_persist_path: None,
+            field_encryption_key: None,
+            enable_http_cache: false,
+            leaderboard_cache_ttl_secs: 300,
+            response_timeout_ms: 10_000,
+            response_format: ResponseFormat::Json,
+            log_sample_rate: 1.0,
+            log_format: LogFormat::Plain,
+            environment: Environment::Production,
+            chaos: None,
+            registration_token: None,
+            registration_link: None,
+            registration_link_ttl_secs: 3600,
+            airtable_webhook_token: None,
+            admin_secret: None,
+            api_keys: Vec::new(),
+            webhook_urls: Vec::new(),
+            alert_webhook_url: None,
+            health_check_account_id: None,
+            health_check_interval_secs: 60,
+            health_check_expected_status: crate::KycStatus::Approved,
+            auto_resubmit_on_expiry: false,
+            kyc_expiry_days: 365,
+            expiry_warn_days: 30,
+            cors_allowed_origins: Vec::new(),
+            trusted_proxy_cidrs: Vec::new(),
+            airtable_proxy: None,
+            blocked_user_agents: None,
+            required_airtable_fields: Vec::new(),
+            kyc_bypass_accounts: Default::default(),
+            fallback_static_file: None,
+            fallback_file_reload_secs: 300,
+            near_network: NearNetwork::Mainnet,
+            near_rpc_url: NearNetwork::Mainnet.default_rpc_url().to_string(),
+            require_tx_proof: false,
+        }
+    }
+}
+
+/// A missing or invalid secret.
+#[derive(Debug)]
+pub(crate) struct ConfigError {
+    pub key: &'static str,
+    pub expected: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} (expected {})",
+            self.key, self.message, self.expected
+        )
+    }
+}
+
+impl Config {
+    /// Reads the whole config, reporting every missing or invalid secret at once instead of
+    /// stopping at the first one.
+    pub(crate) fn from_secrets(
+        secret_store: &dyn SecretStoreExt,
+    ) -> Result<Self, Vec<ConfigError>> {
+        let defaults = Self::default();
+        let mut secrets = SecretReader {
+            secret_store,
+            errors: Vec::new(),
+        };
+
+        let airtable_api_key = secrets.required("AIRTABLE_API_KEY", "an Airtable API token");
+
+        let cors_allowed_origins = secrets
+            .list("CORS_ALLOWED_ORIGINS")
+            .iter()
+            .map(|origin| HeaderValue::from_str(origin))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|_| {
+                secrets.errors.push(ConfigError {
+                    key: "CORS_ALLOWED_ORIGINS",
+                    expected: "a comma-separated list of origins",
+                    message: "contains an invalid origin".to_string(),
+                });
+                Vec::new()
+            });
+
+        let trusted_proxy_cidrs = secrets
+            .list("TRUSTED_PROXY_CIDRS")
+            .iter()
+            .map(|cidr| cidr.parse())
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|_| {
+                secrets.errors.push(ConfigError {
+                    key: "TRUSTED_PROXY_CIDRS",
+                    expected: "a comma-separated list of CIDRs, e.g. `10.0.0.0/8`",
+                    message: "contains an invalid CIDR".to_string(),
+                });
+                Vec::new()
+            });
+
+        // The secrets take precedence over the environment variables commonly used for proxies.
+        let airtable_proxy = secrets
+            .optional("HTTPS_PROXY")
+            .or_else(|| std::env::var("HTTPS_PROXY").ok())
+            .and_then(|proxy_url| {
+                let no_proxy = secrets
+                    .optional("NO_PROXY")
+                    .or_else(|| std::env::var("NO_PROXY").ok())
+                    .unwrap_or_default();
+                reqwest::Proxy::https(&proxy_url)
+                    .map(|proxy| proxy.no_proxy(reqwest::NoProxy::from_string(&no_proxy)))
+                    .map_err(|err| {
+                        secrets.errors.push(ConfigError {
+                            key: "HTTPS_PROXY",
+                            expected: "a proxy URL, e.g. `http://proxy.example.com:3128`",
+                            message: err.to_string(),
+                        })
+                    })
+                    .ok()
+            });
+
+        let field_encryption_key = secrets.optional("FIELD_ENCRYPTION_KEY").and_then(|key| {
+            crate::encryption::parse_key(&key)
+                .map_err(|message| {
+                    secrets.errors.push(ConfigError {
+                        key: "FIELD_ENCRYPTION_KEY",
+                        expected:
+                            "a 32-byte key as 64 hex digits, e.g. from `openssl rand -hex 32`",
+                        message,
+                    })
+                })
+                .ok()
+        });
+
+        let kyc_bypass_accounts: std::collections::HashMap<_, _> = secrets
+            .optional("KYC_BYPASS_ACCOUNTS")
+            .and_then(|accounts| {
+                serde_json::from_str(&accounts)
+                    .map_err(|err| {
+                        secrets.errors.push(ConfigError {
+                            key: "KYC_BYPASS_ACCOUNTS",
+                            expected: r#"a JSON map of account IDs to KYC statuses, e.g. `{"test.near": "APPROVED"}`"#,
+                            message: err.to_string(),
+                        })
+                    })
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        let near_network = secrets.parse(
+            "NEAR_NETWORK",
+            defaults.near_network,
+            "`mainnet` or `testnet`",
+        );
+        let airtable_base_id = match near_network {
+            NearNetwork::Mainnet => defaults.airtable_base_id,
+            NearNetwork::Testnet => {
+                secrets.required("AIRTABLE_TESTNET_BASE_ID", "an Airtable base ID")
+            }
+        };
+
+        let blocked_user_agents = secrets.optional("BLOCKED_USER_AGENTS").and_then(|_| {
+            regex::RegexSet::new(secrets.list("BLOCKED_USER_AGENTS"))
+                .map_err(|err| {
+                    secrets.errors.push(ConfigError {
+                        key: "BLOCKED_USER_AGENTS",
+                        expected: "a comma-separated list of regular expressions",
+                        message: err.to_string(),
+                    })
+                })
+                .ok()
+        });
+
+        let health_check_account_id =
+            secrets
+                .optional("HEALTH_CHECK_ACCOUNT_ID")
+                .and_then(|account_id| {
+                    account_id
+                        .parse()
+                        .map_err(|err: near_account_id::ParseAccountError| {
+                            secrets.errors.push(ConfigError {
+                                key: "HEALTH_CHECK_ACCOUNT_ID",
+                                expected: "a NEAR account ID",
+                                message: err.to_string(),
+                            })
+                        })
+                        .ok()
+                });
+
+        let registration_link = secrets.optional("KYC_PORTAL_URL").and_then(|portal_url| {
+            let portal_url = reqwest::Url::parse(&portal_url)
+                .map_err(|err| {
+                    secrets.errors.push(ConfigError {
+                        key: "KYC_PORTAL_URL",
+                        expected: "a URL, e.g. `https://kyc-portal.example.com/start`",
+                        message: err.to_string(),
+                    })
+                })
+                .ok();
+            let secret = secrets.required(
+                "REGISTRATION_LINK_SECRET",
+                "a secret the KYC portal links are signed with",
+            );
+            Some(crate::registration_link::RegistrationLinkConfig {
+                portal_url: portal_url?,
+                secret,
+            })
+        });
+
+        let environment = secrets.parse(
+            "ENVIRONMENT",
+            defaults.environment,
+            "`production`, `development` or `test`",
+        );
+        let chaos = ChaosConfig {
+            latency_ms: secrets.parse("SIMULATE_LATENCY_MS", 0, "a number of milliseconds"),
+            error_rate: secrets
+                .parse(
+                    "SIMULATE_ERROR_RATE",
+                    0.0_f64,
+                    "a number between 0.0 and 1.0",
+                )
+                .clamp(0.0, 1.0),
+        };
+        let chaos = (chaos != ChaosConfig::default()).then_some(chaos);
+        if chaos.is_some() && environment == Environment::Production {
+            secrets.errors.push(ConfigError {
+                key: "ENVIRONMENT",
+                expected: "`development` or `test` to simulate latency or errors",
+                message:
+                    "SIMULATE_LATENCY_MS and SIMULATE_ERROR_RATE are not allowed in production"
+                        .to_string(),
+            });
+        }
+        if !kyc_bypass_accounts.is_empty() && environment == Environment::Production {
+            secrets.errors.push(ConfigError {
+                key: "ENVIRONMENT",
+                expected: "`development` or `test` to bypass the KYC lookup of test accounts",
+                message: "KYC_BYPASS_ACCOUNTS is not allowed in production".to_string(),
+            });
+        }
+
+        let airtable_retry_strategy = match secrets.optional("AIRTABLE_RETRY_STRATEGY").as_deref() {
+            None | Some("exponential") => RetryStrategy::Exponential {
+                initial_ms: secrets.parse(
+                    "AIRTABLE_RETRY_INITIAL_MS",
+                    DEFAULT_RETRY_INITIAL_MS,
+                    "a number of milliseconds",
+                ),
+                multiplier: secrets.parse(
+                    "AIRTABLE_RETRY_MULTIPLIER",
+                    DEFAULT_RETRY_MULTIPLIER,
+                    "a number, e.g. `2.0`",
+                ),
+                max_ms: secrets.parse(
+                    "AIRTABLE_RETRY_MAX_MS",
+                    DEFAULT_RETRY_MAX_MS,
+                    "a number of milliseconds",
+                ),
+            },
+            Some("linear") => RetryStrategy::Linear {
+                delay_ms: secrets.parse(
+                    "AIRTABLE_RETRY_DELAY_MS",
+                    DEFAULT_RETRY_DELAY_MS,
+                    "a number of milliseconds",
+                ),
+            },
+            Some("fixed") => RetryStrategy::Fixed {
+                delay_ms: secrets.parse(
+                    "AIRTABLE_RETRY_DELAY_MS",
+                    DEFAULT_RETRY_DELAY_MS,
+                    "a number of milliseconds",
+                ),
+            },
+            Some(strategy) => {
+                secrets.errors.push(ConfigError {
+                    key: "AIRTABLE_RETRY_STRATEGY",
+                    expected: "`linear`, `exponential` or `fixed`",
+                    message: format!("has an invalid value: {strategy}"),
+                });
+                defaults.airtable_retry_strategy
+            }
+        };
+
+        let airtable_filter_template = secrets.optional("AIRTABLE_FILTER_TEMPLATE");
+        if let Some(template) = &airtable_filter_template {
+            if let Err(message) =
+                crate::account_id::render_filter_template(template, "example.near")
+            {
+                secrets.errors.push(ConfigError {
+                    key: "AIRTABLE_FILTER_TEMPLATE",
+                    expected: "an Airtable formula containing `{account_id}`",
+                    message,
+                });
+            }
+        }
+
+        let config = Self {
+            airtable_api_key,
+            key_rotation_grace_period_secs: secrets.parse(
+                "KEY_ROTATION_GRACE_PERIOD_SECS",
+                defaults.key_rotation_grace_period_secs,
+                "a number of seconds",
+            ),
+            airtable_base_id,
+            airtable_meta_api_key: secrets.optional("AIRTABLE_META_API_KEY"),
+            airtable_base_url: secrets
+                .optional("AIRTABLE_BASE_URL")
+                .unwrap_or(defaults.airtable_base_url),
+            airtable_api_version: secrets.parse(
+                "AIRTABLE_API_VERSION",
+                defaults.airtable_api_version,
+                "`v0` or `v0.1`",
+            ),
+            airtable_max_records: secrets
+                .parse(
+                    "AIRTABLE_MAX_RECORDS",
+                    defaults.airtable_max_records,
+                    "a positive integer",
+                )
+                .clamp(1, MAX_AIRTABLE_MAX_RECORDS),
+            airtable_view_name: secrets
+                .optional("AIRTABLE_VIEW_NAME")
+                .unwrap_or(defaults.airtable_view_name),
+            airtable_view_pool: secrets.list("AIRTABLE_VIEW_POOL"),
+            airtable_allowed_views: secrets.list("AIRTABLE_ALLOWED_VIEWS"),
+            airtable_follow_linked_records: secrets.parse(
+                "AIRTABLE_FOLLOW_LINKED_RECORDS",
+                defaults.airtable_follow_linked_records,
+                "`true` or `false`",
+            ),
+            airtable_max_concurrent_requests: secrets
+                .parse(
+                    "AIRTABLE_MAX_CONCURRENT_REQUESTS",
+                    defaults.airtable_max_concurrent_requests,
+                    "a positive integer",
+                )
+                .max(1),
+            airtable_retry_budget_ms: secrets.parse(
+                "AIRTABLE_RETRY_BUDGET_MS",
+                defaults.airtable_retry_budget_ms,
+                "a number of milliseconds",
+            ),
+            airtable_retry_strategy,
+            max_request_body_bytes: secrets.parse(
+                "MAX_REQUEST_BODY_BYTES",
+                defaults.max_request_body_bytes,
+                "a number of bytes",
+            ),
+            max_url_length: secrets.parse(
+                "MAX_URL_LENGTH",
+                defaults.max_url_length,
+                "a number of characters",
+            ),
+            airtable_writeback: secrets.parse(
+                "AIRTABLE_WRITEBACK",
+                defaults.airtable_writeback,
+                "`true` or `false`",
+            ),
+            gdpr_delete_from_airtable: secrets.parse(
+                "GDPR_DELETE_FROM_AIRTABLE",
+                defaults.gdpr_delete_from_airtable,
+                "`true` or `false`",
+            ),
+            airtable_monthly_quota: secrets.parse(
+                "AIRTABLE_MONTHLY_QUOTA",
+                defaults.airtable_monthly_quota,
+                "a number of API calls",
+            ),
+            circuit_breaker_failure_threshold: secrets.parse(
+                "CIRCUIT_BREAKER_FAILURE_THRESHOLD",
+                defaults.circuit_breaker_failure_threshold,
+                "a positive integer",
+            ),
+            circuit_breaker_open_secs: secrets.parse(
+                "CIRCUIT_BREAKER_OPEN_SECS",
+                defaults.circuit_breaker_open_secs,
+                "a number of seconds",
+            ),
+            kyc_cache_ttl_secs: secrets.parse(
+                "KYC_CACHE_TTL_SECS",
+                defaults.kyc_cache_ttl_secs,
+                "a number of seconds",
+            ),
+            stale_kyc_cache_max_entries: secrets.parse(
+                "STALE_KYC_CACHE_MAX_ENTRIES",
+                defaults.stale_kyc_cache_max_entries,
+                "a number of statuses",
+            ),
+            kyc_cache_persist_path: secrets.optional("KYC_CACHE_PERSIST_PATH").map(Into::into),
+            kyc_cache_persist_interval_secs: secrets.parse(
+                "KYC_CACHE_PERSIST_INTERVAL_SECS",
+                defaults.kyc_cache_persist_interval_secs,
+                "a number of seconds",
+            ),
+            ownership_nonce_persist_path: secrets
+                .optional("OWNERSHIP_NONCE_PERSIST_PATH")
+                .map(Into::into),
+            field_encryption_key,
+            enable_http_cache: secrets.parse(
+                "ENABLE_HTTP_CACHE",
+                defaults.enable_http_cache,
+                "`true` or `false`",
+            ),
+            leaderboard_cache_ttl_secs: secrets.parse(
+                "LEADERBOARD_CACHE_TTL_SECS",
+                defaults.leaderboard_cache_ttl_secs,
+                "a number of seconds",
+            ),
+            response_timeout_ms: secrets.parse(
+                "RESPONSE_TIMEOUT_MS",
+                defaults.response_timeout_ms,
+                "a number of milliseconds",
+            ),
+            response_format: secrets.parse(
+                "RESPONSE_FORMAT",
+                defaults.response_format,
+                "`json` or `hal`",
+            ),
+            log_sample_rate: secrets
+                .parse(
+                    "LOG_SAMPLE_RATE",
+                    defaults.log_sample_rate,
+                    "a number between 0.0 and 1.0",
+                )
+                .clamp(0.0, 1.0),
+            log_format: secrets.parse("LOG_FORMAT", defaults.log_format, "`plain` or `ecs`"),
+            registration_token: secrets.optional("REGISTRATION_TOKEN"),
+            registration_link,
+            registration_link_ttl_secs: secrets
+                .parse(
+                    "REGISTRATION_LINK_TTL_SECS",
+                    defaults.registration_link_ttl_secs,
+                    "a positive number of seconds",
+                )
+                .max(1),
+            airtable_webhook_token: secrets.optional("AIRTABLE_WEBHOOK_TOKEN"),
+            admin_secret: secrets.optional("ADMIN_SECRET"),
+            api_keys: secrets.list("API_KEYS"),
+            webhook_urls: secrets.list("WEBHOOK_URLS"),
+            alert_webhook_url: secrets.optional("ALERT_WEBHOOK_URL"),
+            health_check_account_id,
+            health_check_interval_secs: secrets
+                .parse(
+                    "HEALTH_CHECK_INTERVAL_SECS",
+                    defaults.health_check_interval_secs,
+                    "a positive number of seconds",
+                )
+                .max(1),
+            health_check_expected_status: secrets.parse(
+                "HEALTH_CHECK_EXPECTED_STATUS",
+                defaults.health_check_expected_status,
+                "a KYC status, e.g. `APPROVED`",
+            ),
+            auto_resubmit_on_expiry: secrets.parse(
+                "AUTO_RESUBMIT_ON_EXPIRY",
+                defaults.auto_resubmit_on_expiry,
+                "`true` or `false`",
+            ),
+            kyc_expiry_days: secrets.parse(
+                "KYC_EXPIRY_DAYS",
+                defaults.kyc_expiry_days,
+                "a number of days",
+            ),
+            expiry_warn_days: secrets.parse(
+                "EXPIRY_WARN_DAYS",
+                defaults.expiry_warn_days,
+                "a number of days",
+            ),
+            airtable_filter_template,
+            environment,
+            chaos,
+            blocked_user_agents,
+            required_airtable_fields: secrets.list("REQUIRED_AIRTABLE_FIELDS"),
+            tie_breaking_policy: secrets.parse(
+                "TIE_BREAKING_POLICY",
+                defaults.tie_breaking_policy,
+                "`PreferVerified`, `MostRecent`, `Strictest` or `Lenient`",
+            ),
+            all_chains_policy: secrets.parse(
+                "ALL_CHAINS_POLICY",
+                defaults.all_chains_policy,
+                "`MostPermissive` or `MostRestrictive`",
+            ),
+            cors_allowed_origins,
+            trusted_proxy_cidrs,
+            airtable_proxy,
+            kyc_bypass_accounts,
+            fallback_static_file: secrets.optional("FALLBACK_STATIC_FILE").map(Into::into),
+            fallback_file_reload_secs: secrets.parse(
+                "FALLBACK_FILE_RELOAD_SECS",
+                defaults.fallback_file_reload_secs,
+                "a number of seconds",
+            ),
+            near_network,
+            near_rpc_url: secrets
+                .optional("NEAR_RPC_URL")
+                .unwrap_or_else(|| near_network.default_rpc_url().to_string()),
+            require_tx_proof: secrets.parse(
+                "REQUIRE_TX_PROOF",
+                defaults.require_tx_proof,
+                "`true` or `false`",
+            ),
+        };
+
+        if secrets.errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(secrets.errors)
+        }
+    }
+}
+
+/// Source of the secrets, so the configuration can be read from something other than the Shuttle
+/// secret store.
+pub(crate) trait SecretStoreExt {
+    fn get_secret(&self, key: &str) -> Option<String>;
+}
+
+impl SecretStoreExt for SecretStore {
+    fn get_secret(&self, key: &str) -> Option<String> {
+        self.get(key)
+    }
+}
+
+/// In-memory secrets for the tests.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct HashMapSecretStore(pub std::collections::HashMap<String, String>);
+
+#[cfg(test)]
+impl HashMapSecretStore {
+    pub(crate) fn with(mut self, key: &str, value: &str) -> Self {
+        self.0.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+impl SecretStoreExt for HashMapSecretStore {
+    fn get_secret(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+}
+
+/// Reads secrets, collecting the errors instead of returning early.
+struct SecretReader<'a> {
+    secret_store: &'a dyn SecretStoreExt,
+    errors: Vec<ConfigError>,
+}
+
+impl SecretReader<'_> {
+    fn optional(&self, key: &str) -> Option<String> {
+        self.secret_store.get_secret(key)
+    }
+
+    fn required(&mut self, key: &'static str, expected: &'static str) -> String {
+        self.optional(key).unwrap_or_else(|| {
+            self.errors.push(ConfigError {
+                key,
+                expected,
+                message: "was not found".to_string(),
+            });
+            String::new()
+        })
+    }
+
+    fn parse<T: std::str::FromStr>(
+        &mut self,
+        key: &'static str,
+        default: T,
+        expected: &'static str,
+    ) -> T {
+        match self.optional(key) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                self.errors.push(ConfigError {
+                    key,
+                    expected,
+                    message: format!("has an invalid value: {value}"),
+                });
+                default
+            }),
+            None => default,
+        }
+    }
+
+    /// Parses a comma-separated secret value, ignoring empty items.
+    fn list(&self, key: &str) -> Vec<String> {
+        self.optional(key)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,140 @@
+//! Log events in the Elastic Common Schema, for `LOG_FORMAT=ecs`.
+
+use tracing_subscriber::{
+    fmt::{format, FmtContext, FormatEvent, FormatFields},
+    registry::LookupSpan,
+};
+
+/// Formats every event as a single line JSON object with the ECS field names. The event fields
+/// without an ECS equivalent are kept under their own names.
+pub(crate) struct EcsFormat;
+
+impl<S, N> FormatEvent<S, N> for EcsFormat
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> std::fmt::Result {
+        let metadata = event.metadata();
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+
+        let mut log_event = serde_json::Map::new();
+        log_event.insert(
+            "@timestamp".to_string(),
+            chrono::Utc::now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        log_event.insert(
+            "log.level".to_string(),
+            metadata.level().as_str().to_lowercase().into(),
+        );
+        log_event.insert("log.logger".to_string(), metadata.target().into());
+        log_event.insert("service.name".to_string(), env!("CARGO_PKG_NAME").into());
+        log_event.insert(
+            "service.version".to_string(),
+            env!("CARGO_PKG_VERSION").into(),
+        );
+        log_event.insert("ecs.version".to_string(), "8.11".into());
+        for (name, value) in fields.0 {
+            let name = match name.as_str() {
+                "account_id" => "user.id".to_string(),
+                _ => name,
+            };
+            log_event.insert(name, value);
+        }
+        writeln!(writer, "{}", serde_json::Value::Object(log_event))
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor(serde_json::Map<String, serde_json::Value>);
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &tracing::field::Field, value: serde_json::Value) {
+        self.0.insert(field.name().to_string(), value);
+    }
+}
+
+impl tracing::field::Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Collects the formatted events.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn events_are_formatted_as_ecs_json() {
+        let output = Output::default();
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(EcsFormat)
+            .with_writer({
+                let output = output.clone();
+                move || output.clone()
+            })
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(account_id = "alice.near", attempts = 3, "Lookup failed");
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        let event: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(event["log.level"], "warn");
+        assert_eq!(event["log.logger"], module_path!());
+        assert_eq!(event["message"], "Lookup failed");
+        assert_eq!(event["user.id"], "alice.near");
+        assert_eq!(event["attempts"], 3);
+        assert_eq!(event["ecs.version"], "8.11");
+        assert_eq!(event["service.name"], env!("CARGO_PKG_NAME"));
+        assert!(event["@timestamp"]
+            .as_str()
+            .is_some_and(|timestamp| chrono::DateTime::parse_from_rfc3339(timestamp).is_ok()));
+        assert!(event.get("account_id").is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! Encryption of the personal data the proxy keeps outside of Airtable, with the
+//! `FIELD_ENCRYPTION_KEY`.
+
+use anyhow::anyhow;
+use base64::Engine;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+/// Parses a 32-byte key written as 64 hex digits.
+pub(crate) fn parse_key(hex: &str) -> Result<[u8; 32], String> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err("is not 64 hex digits long".to_string());
+    }
+    let mut key = [0; 32];
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let digits = std::str::from_utf8(digits).expect("the key is ASCII");
+        *byte = u8::from_str_radix(digits, 16)
+            .map_err(|_| format!("contains non-hex digits: {digits}"))?;
+    }
+    Ok(key)
+}
+
+/// A value serialized as JSON and encrypted with AES-256-GCM, stored as the base64 encoding of the
+/// nonce followed by the ciphertext and its tag.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub(crate) struct EncryptedBytes(String);
+
+/// Encrypts and decrypts values with the `FIELD_ENCRYPTION_KEY`. Each value gets a random nonce,
+/// so equal values do not have equal ciphertexts.
+pub(crate) struct FieldEncryptor {
+    key: LessSafeKey,
+    random: SystemRandom,
+}
+
+impl FieldEncryptor {
+    pub(crate) fn new(key: &[u8; 32]) -> Self {
+        Self {
+            key: LessSafeKey::new(
+                UnboundKey::new(&AES_256_GCM, key).expect("the key is 32 bytes long"),
+            ),
+            random: SystemRandom::new(),
+        }
+    }
+
+    pub(crate) fn encrypt<T: serde::Serialize>(&self, value: &T) -> EncryptedBytes {
+        let mut nonce = [0; NONCE_LEN];
+        self.random
+            .fill(&mut nonce)
+            .expect("the system random number generator is available");
+        let mut ciphertext = serde_json::to_vec(value).expect("the value is serializable");
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut ciphertext,
+            )
+            .expect("the value is small enough to encrypt");
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+        EncryptedBytes(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    /// Fails for values encrypted with another key or tampered with.
+    pub(crate) fn decrypt<T: serde::de::DeserializeOwned>(
+        &self,
+        encrypted: &EncryptedBytes,
+    ) -> anyhow::Result<T> {
+        let mut bytes = base64::engine::general_purpose::STANDARD.decode(&encrypted.0)?;
+        if bytes.len() < NONCE_LEN + AES_256_GCM.tag_len() {
+            return Err(anyhow!("the encrypted value is too short"));
+        }
+        let mut ciphertext = bytes.split_off(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(&bytes).map_err(|_| anyhow!("invalid nonce"))?;
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut ciphertext)
+            .map_err(|_| anyhow!("the value could not be decrypted"))?;
+        Ok(serde_json::from_slice(plaintext)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KycStatus;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn values_round_trip() {
+        let encryptor = FieldEncryptor::new(&parse_key(KEY).unwrap());
+        let value = ("alice.near".to_string(), KycStatus::Approved);
+
+        let encrypted = encryptor.encrypt(&value);
+        assert!(!encrypted.0.contains("alice"));
+        assert_ne!(encrypted, encryptor.encrypt(&value));
+        assert_eq!(
+            encryptor
+                .decrypt::<(String, KycStatus)>(&encrypted)
+                .unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn other_keys_cannot_decrypt() {
+        let encrypted = FieldEncryptor::new(&parse_key(KEY).unwrap()).encrypt(&"alice.near");
+        let other_encryptor = FieldEncryptor::new(&[7; 32]);
+        assert!(other_encryptor.decrypt::<String>(&encrypted).is_err());
+    }
+
+    #[test]
+    fn keys_are_64_hex_digits() {
+        assert_eq!(parse_key(KEY).unwrap()[31], 0x1f);
+        assert!(parse_key(&KEY[2..]).is_err());
+        assert!(parse_key(&KEY.replace('a', "g")).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    fetch_all_airtable_records, timeline::TimelineEvent, AppState, KycApprovalStanding, KycStatus,
+};
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+#[derive(serde::Serialize)]
+struct ExpiryWarning {
+    event: &'static str,
+    account_id: String,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    days_remaining: i64,
+}
+
+/// How long a webhook endpoint has to respond, so a hanging endpoint does not hold up the
+/// notifications of the other accounts.
+const WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Events waiting for [`run_webhook_deliveries`], beyond which new events are dropped.
+const MAX_QUEUED_EVENTS: usize = 1000;
+
+/// An event about an account, queued for delivery.
+struct QueuedEvent {
+    account_id: String,
+    event: serde_json::Value,
+}
+
+/// Delivers events to every configured `WEBHOOK_URLS` endpoint.
+pub(crate) struct WebhookNotifier {
+    /// Built by the first delivery, as most deployments have no webhooks.
+    client: tokio::sync::OnceCell<reqwest::Client>,
+    queue: tokio::sync::mpsc::Sender<QueuedEvent>,
+    /// Taken by [`run_webhook_deliveries`].
+    queued_events: std::sync::Mutex<Option<tokio::sync::mpsc::Receiver<QueuedEvent>>>,
+}
+
+impl Default for WebhookNotifier {
+    fn default() -> Self {
+        let (queue, queued_events) = tokio::sync::mpsc::channel(MAX_QUEUED_EVENTS);
+        Self {
+            client: Default::default(),
+            queue,
+            queued_events: std::sync::Mutex::new(Some(queued_events)),
+        }
+    }
+}
+
+impl WebhookNotifier {
+    /// Queues the event for delivery by [`run_webhook_deliveries`], for the callers which cannot
+    /// wait for the webhooks. Nothing is queued when no webhooks are configured.
+    pub(crate) fn enqueue<T: serde::Serialize>(
+        &self,
+        state: &AppState,
+        account_id: &str,
+        event: &T,
+    ) {
+        if state.config.webhook_urls.is_empty() {
+            return;
+        }
+        let event = QueuedEvent {
+            account_id: account_id.to_string(),
+            event: serde_json::to_value(event).expect("the webhook events are serializable"),
+        };
+        if self.queue.try_send(event).is_err() {
+            tracing::warn!(%account_id, "webhook queue is full, the event was dropped");
+        }
+    }
+
+    /// Sends the event about the account to every webhook, recording the deliveries in the
+    /// account timeline. Returns whether every webhook received it.
+    pub(crate) async fn notify<T: serde::Serialize>(
+        &self,
+        state: &AppState,
+        account_id: &str,
+        event: &T,
+    ) -> bool {
+        let client = self
+            .client
+            .get_or_try_init(|| async {
+                reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build()
+            })
+            .await;
+        let client = match client {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::warn!(%account_id, "failed to build the webhook HTTP client: {err}");
+                return false;
+            }
+        };
+        let mut delivered = true;
+        for webhook_url in &state.config.webhook_urls {
+            match client
+                .post(webhook_url)
+                .json(event)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                Ok(_) => {
+                    if let Ok(account_id) = account_id.parse() {
+                        state.timeline.record(
+                            &account_id,
+                            TimelineEvent::WebhookDelivered {
+                                url: webhook_url.clone(),
+                            },
+                        );
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(%webhook_url, %account_id, "failed to deliver webhook event: {err}");
+                    delivered = false;
+                }
+            }
+        }
+        delivered
+    }
+}
+
+/// Delivers the events queued with [`WebhookNotifier::enqueue`], one at a time.
+pub(crate) async fn run_webhook_deliveries(state: Arc<AppState>) {
+    let Some(mut queued_events) = state.webhook_notifier.queued_events.lock().unwrap().take()
+    else {
+        return;
+    };
+    while let Some(queued_event) = queued_events.recv().await {
+        state
+            .webhook_notifier
+            .notify(&state, &queued_event.account_id, &queued_event.event)
+            .await;
+    }
+}
+
+/// Once a day, finds approved accounts whose KYC expires within `EXPIRY_WARN_DAYS` and
+/// notifies every configured webhook about them.
+pub(crate) async fn run_expiry_notifications(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    // Expiry dates the accounts were warned about, so every approval is only warned about once.
+    // Not kept across restarts, so a restart can repeat a warning.
+    let mut warned = HashMap::new();
+    loop {
+        interval.tick().await;
+
+        if send_expiry_warnings(&state, &mut warned).await.is_err() {
+            tracing::warn!("failed to fetch KYC records for expiry notifications");
+        }
+    }
+}
+
+/// Warns about the expiring accounts which were not warned about their current expiry date yet.
+/// An account is warned again on the next run unless every webhook received the warning.
+async fn send_expiry_warnings(
+    state: &AppState,
+    warned: &mut HashMap<String, chrono::DateTime<chrono::Utc>>,
+) -> Result<(), crate::KycError> {
+    let warnings = crate::circuit_breaker::in_batch_circuit(find_expiring_accounts(state)).await?;
+    let now = chrono::Utc::now();
+    warned.retain(|_, expires_at| *expires_at > now);
+    for warning in warnings {
+        if warned.get(&warning.account_id) == Some(&warning.expires_at) {
+            continue;
+        }
+        if state
+            .webhook_notifier
+            .notify(state, &warning.account_id, &warning)
+            .await
+        {
+            warned.insert(warning.account_id, warning.expires_at);
+        }
+    }
+    Ok(())
+}
+
+async fn find_expiring_accounts(state: &AppState) -> Result<Vec<ExpiryWarning>, crate::KycError> {
+    let now = chrono::Utc::now();
+    let mut warnings = Vec::new();
+    let records = fetch_all_airtable_records(
+        state,
+        "AND(LOWER({status})='approved', {approval_standing}='active')",
+        None,
+    )
+    .await?;
+
+    for record in records {
+        if !record.is_near_record() {
+            continue;
+        }
+        let fields = record.fields;
+        let (Some(account_id), Some(approval_date)) = (fields.near_wallet, fields.approval_date)
+        else {
+            continue;
+        };
+        if !matches!(fields.status, KycStatus::Approved)
+            || !matches!(fields.approval_standing, KycApprovalStanding::Active)
+        {
+            continue;
+        }
+
+        let expires_at = approval_date + chrono::Duration::days(state.config.kyc_expiry_days);
+        let days_remaining = (expires_at - now).num_days();
+        if expires_at > now && days_remaining <= state.config.expiry_warn_days {
+            warnings.push(ExpiryWarning {
+                event: "kyc_expiry_warning",
+                account_id,
+                expires_at,
+                days_remaining,
+            });
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, mock_airtable::MockAirtable};
+
+    fn approval(account_id: &str, age_days: i64) -> serde_json::Value {
+        serde_json::json!({
+            "near_wallet": account_id,
+            "status": "approved",
+            "approval_standing": "active",
+            "approval_date": chrono::Utc::now() - chrono::Duration::days(age_days),
+        })
+    }
+
+    fn seed_approvals(airtable: &MockAirtable) {
+        airtable.seed_record(approval("fresh.near", 10));
+        airtable.seed_record(approval("expiring.near", 340));
+        airtable.seed_record(approval("last-day.near", 364));
+        airtable.seed_record(approval("expired.near", 400));
+        airtable.seed_record(serde_json::json!({
+            "near_wallet": "pending.near",
+            "status": "pending",
+            "approval_standing": "active",
+            "approval_date": chrono::Utc::now() - chrono::Duration::days(340),
+        }));
+    }
+
+    #[tokio::test]
+    async fn approvals_within_the_warning_period_are_selected() {
+        let airtable = MockAirtable::start().await;
+        seed_approvals(&airtable);
+        let state = AppState::builder().with_config(airtable.config()).build();
+
+        let warnings = find_expiring_accounts(&state).await.unwrap();
+        let mut accounts = warnings
+            .iter()
+            .map(|warning| (warning.account_id.as_str(), warning.days_remaining))
+            .collect::<Vec<_>>();
+        accounts.sort_unstable();
+        assert_eq!(accounts, [("expiring.near", 24), ("last-day.near", 0)]);
+    }
+
+    #[tokio::test]
+    async fn every_approval_is_warned_about_once() {
+        let airtable = MockAirtable::start().await;
+        seed_approvals(&airtable);
+        let state = AppState::builder()
+            .with_config(Config {
+                webhook_urls: vec![format!("{}/webhook", airtable.base_url())],
+                ..airtable.config()
+            })
+            .build();
+        let webhook_events = || {
+            airtable
+                .requests()
+                .into_iter()
+                .filter(|request| request.path == "/webhook")
+                .map(|request| request.body)
+                .collect::<Vec<_>>()
+        };
+
+        let mut warned = HashMap::new();
+        send_expiry_warnings(&state, &mut warned).await.unwrap();
+        let events = webhook_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["event"], "kyc_expiry_warning");
+        assert!(events[0]["expires_at"].is_string());
+
+        send_expiry_warnings(&state, &mut warned).await.unwrap();
+        assert_eq!(webhook_events().len(), 2);
+        let account_id = "expiring.near".parse().unwrap();
+        assert!(matches!(
+            state.timeline.get(&account_id)[..],
+            [crate::timeline::TimelineEntry {
+                event: TimelineEvent::WebhookDelivered { .. },
+                ..
+            }]
+        ));
+    }
+}
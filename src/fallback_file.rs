@@ -0,0 +1,124 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use crate::{AppState, KycStatus};
+
+/// Pre-exported KYC statuses by account, served when Airtable is unavailable.
+#[derive(Default)]
+pub(crate) struct FallbackStatuses {
+    statuses: RwLock<HashMap<near_account_id::AccountId, KycStatus>>,
+}
+
+impl FallbackStatuses {
+    pub(crate) fn get(&self, account_id: &near_account_id::AccountId) -> Option<KycStatus> {
+        self.statuses.read().unwrap().get(account_id).copied()
+    }
+
+    fn replace(&self, statuses: HashMap<near_account_id::AccountId, KycStatus>) {
+        *self.statuses.write().unwrap() = statuses;
+    }
+}
+
+/// Reads the `FALLBACK_STATIC_FILE` on startup and then every `FALLBACK_FILE_RELOAD_SECS`. The
+/// previously read statuses are kept when the file cannot be read.
+pub(crate) async fn run_fallback_file_reload(state: Arc<AppState>, path: PathBuf) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        state.config.fallback_file_reload_secs,
+    ));
+    loop {
+        interval.tick().await;
+        load_fallback_file(&state.fallback_statuses, &path).await;
+    }
+}
+
+async fn load_fallback_file(fallback_statuses: &FallbackStatuses, path: &Path) {
+    let contents = match tokio::fs::read(path).await {
+        Ok(contents) => contents,
+        Err(err) => {
+            tracing::warn!(path = %path.display(), "failed to read the fallback file: {err}");
+            return;
+        }
+    };
+    match serde_json::from_slice::<HashMap<_, _>>(&contents) {
+        Ok(statuses) => {
+            tracing::info!(path = %path.display(), accounts = statuses.len(), "Fallback file was loaded");
+            fallback_statuses.replace(statuses);
+        }
+        Err(err) => {
+            tracing::warn!(path = %path.display(), "failed to parse the fallback file: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::{
+        config::Config,
+        mock_airtable::{self, MockAirtable},
+    };
+
+    fn alice() -> near_account_id::AccountId {
+        "alice.near".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn statuses_are_loaded_from_the_file() {
+        let path = std::env::temp_dir().join(format!("fallback_{}.json", uuid::Uuid::new_v4()));
+        let statuses = FallbackStatuses::default();
+
+        // A missing file leaves the statuses empty
+        load_fallback_file(&statuses, &path).await;
+        assert_eq!(statuses.get(&alice()), None);
+
+        tokio::fs::write(&path, r#"{"alice.near": "APPROVED"}"#)
+            .await
+            .unwrap();
+        load_fallback_file(&statuses, &path).await;
+        assert_eq!(statuses.get(&alice()), Some(KycStatus::Approved));
+
+        // The previous statuses are kept when the file does not parse
+        tokio::fs::write(&path, "not json").await.unwrap();
+        load_fallback_file(&statuses, &path).await;
+        assert_eq!(statuses.get(&alice()), Some(KycStatus::Approved));
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn statuses_are_served_while_the_circuit_is_open() {
+        let airtable = MockAirtable::start().await;
+        airtable.fail_with(StatusCode::INTERNAL_SERVER_ERROR);
+        let config = |circuit_breaker_failure_threshold| Config {
+            circuit_breaker_failure_threshold,
+            kyc_cache_ttl_secs: 0,
+            ..airtable.config()
+        };
+
+        // A single failure does not open the circuit, so the fallback file is not used yet
+        let (state, router) = airtable.app(config(5));
+        state
+            .fallback_statuses
+            .replace([(alice(), KycStatus::Approved)].into());
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_ne!(status, StatusCode::OK);
+
+        let (state, router) = airtable.app(config(1));
+        state
+            .fallback_statuses
+            .replace([(alice(), KycStatus::Approved)].into());
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+        assert_eq!(body["source"], "fallback_file");
+
+        // Accounts missing from the file still fail
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/bob.near").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
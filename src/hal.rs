@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+
+#[derive(serde::Serialize)]
+pub(crate) struct HalLink {
+    pub href: String,
+}
+
+/// A resource wrapped in a HAL envelope, with its links under `_links` next to its own fields.
+#[derive(serde::Serialize)]
+pub(crate) struct HalResponse<T> {
+    #[serde(rename = "_links")]
+    pub links: BTreeMap<&'static str, HalLink>,
+    #[serde(flatten)]
+    pub resource: T,
+}
+
+impl<T> HalResponse<T> {
+    /// Wraps the KYC status of the account, linking to the related endpoints.
+    pub(crate) fn kyc_status(account_id: &near_account_id::AccountId, resource: T) -> Self {
+        let link = |path: &str| HalLink {
+            href: format!("/v1/kyc/{account_id}{path}"),
+        };
+        Self {
+            links: BTreeMap::from([
+                ("self", link("")),
+                ("refresh", link("/refresh")),
+                ("timeline", link("/timeline")),
+            ]),
+            resource,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, extract::Request, http::header};
+    use tower::ServiceExt;
+
+    use crate::{
+        config::{Config, ResponseFormat},
+        mock_airtable::MockAirtable,
+    };
+
+    /// Content type and body of the KYC status of `alice.near` in the response format.
+    async fn kyc_status_response(response_format: ResponseFormat) -> (String, serde_json::Value) {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let (_, router) = airtable.app(Config {
+            response_format,
+            ..airtable.config()
+        });
+
+        let response = router
+            .oneshot(
+                Request::get("/v1/kyc/alice.near")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let content_type = response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (content_type, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn hal_responses_link_the_related_endpoints() {
+        let (content_type, body) = kyc_status_response(ResponseFormat::Hal).await;
+        assert_eq!(content_type, "application/hal+json");
+        assert_eq!(
+            body["_links"],
+            serde_json::json!({
+                "self": { "href": "/v1/kyc/alice.near" },
+                "refresh": { "href": "/v1/kyc/alice.near/refresh" },
+                "timeline": { "href": "/v1/kyc/alice.near/timeline" },
+            })
+        );
+        assert_eq!(body["account_id"], "alice.near");
+        assert_eq!(body["kyc_status"], "APPROVED");
+    }
+
+    #[tokio::test]
+    async fn json_responses_have_no_links() {
+        let (content_type, body) = kyc_status_response(ResponseFormat::Json).await;
+        assert_eq!(content_type, "application/json");
+        assert!(body.get("_links").is_none());
+        assert_eq!(body["kyc_status"], "APPROVED");
+    }
+}
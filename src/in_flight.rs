@@ -0,0 +1,148 @@
+use std::{collections::HashMap, future::Future, hash::Hash, sync::Mutex};
+
+use tokio::sync::broadcast;
+
+use crate::{KycError, KycStatus};
+
+type LookupResult = Result<KycStatus, KycError>;
+
+/// Deduplicates concurrent lookups with the same key (e.g. the account ID), so a burst of requests
+/// for a hot account results in a single Airtable roundtrip which all the waiters share.
+pub(crate) struct InFlightLookups<K> {
+    lookups: Mutex<HashMap<K, broadcast::Sender<LookupResult>>>,
+}
+
+impl<K> Default for InFlightLookups<K> {
+    fn default() -> Self {
+        Self {
+            lookups: Default::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> InFlightLookups<K> {
+    pub(crate) async fn get_or_fetch<F>(&self, key: K, fetch: F) -> LookupResult
+    where
+        F: Future<Output = LookupResult>,
+    {
+        let subscription = {
+            let mut lookups = self.lookups.lock().unwrap();
+            match lookups.get(&key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    lookups.insert(key.clone(), broadcast::channel(1).0);
+                    None
+                }
+            }
+        };
+
+        if let Some(mut receiver) = subscription {
+            // The channel is closed without a value only when the leading request got cancelled,
+            // in which case we do the lookup on our own
+            return match receiver.recv().await {
+                Ok(result) => result,
+                Err(_) => fetch.await,
+            };
+        }
+
+        let mut guard = InFlightGuard {
+            lookups: &self.lookups,
+            key: &key,
+            removed: false,
+        };
+        let result = fetch.await;
+        if let Some(sender) = guard.remove() {
+            // Nobody is subscribed when there were no concurrent requests
+            let _ = sender.send(result.clone());
+        }
+        result
+    }
+}
+
+/// Makes sure the in-flight entry is removed even when the leading request future is dropped.
+struct InFlightGuard<'a, K: Hash + Eq> {
+    lookups: &'a Mutex<HashMap<K, broadcast::Sender<LookupResult>>>,
+    key: &'a K,
+    removed: bool,
+}
+
+impl<K: Hash + Eq> InFlightGuard<'_, K> {
+    fn remove(&mut self) -> Option<broadcast::Sender<LookupResult>> {
+        self.removed = true;
+        self.lookups.lock().unwrap().remove(self.key)
+    }
+}
+
+impl<K: Hash + Eq> Drop for InFlightGuard<'_, K> {
+    fn drop(&mut self) {
+        if !self.removed {
+            self.remove();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn concurrent_lookups_share_one_fetch() {
+        let in_flight = Arc::new(InFlightLookups::default());
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let (release, released) = tokio::sync::watch::channel(false);
+
+        let lookups = (0..5)
+            .map(|_| {
+                let in_flight = in_flight.clone();
+                let fetches = fetches.clone();
+                let mut released = released.clone();
+                tokio::spawn(async move {
+                    in_flight
+                        .get_or_fetch("alice.near", async move {
+                            fetches.fetch_add(1, Ordering::SeqCst);
+                            released.wait_for(|released| *released).await.unwrap();
+                            Ok(KycStatus::Approved)
+                        })
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        // Let all the lookups subscribe before the fetch finishes
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        release.send(true).unwrap();
+
+        for lookup in lookups {
+            assert!(matches!(lookup.await.unwrap(), Ok(KycStatus::Approved)));
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert!(in_flight.lookups.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sequential_lookups_fetch_again() {
+        let in_flight = InFlightLookups::default();
+        for kyc_status in [KycStatus::Pending, KycStatus::Approved] {
+            let result = in_flight
+                .get_or_fetch("alice.near", async move { Ok(kyc_status) })
+                .await;
+            assert!(matches!(result, Ok(status) if status == kyc_status));
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_lookup_is_removed() {
+        let in_flight = InFlightLookups::default();
+        let lookup = in_flight.get_or_fetch("alice.near", std::future::pending());
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(10), lookup)
+                .await
+                .is_err()
+        );
+        assert!(in_flight.lookups.lock().unwrap().is_empty());
+    }
+}
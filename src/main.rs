@@ -1,16 +1,200 @@
-use anyhow::anyhow;
 use axum::{
-    extract::{Path, State},
-    http::Method,
-    routing::get,
+    extract::{Query, State},
+    http::{HeaderValue, Method},
+    response::IntoResponse,
     Json, Router,
 };
 use shuttle_runtime::SecretStore;
 use tower::ServiceBuilder;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+mod account_id;
+mod airtable_client;
+mod audit;
+mod badge;
+mod batch_jobs;
+mod cache;
+mod cache_persist;
+mod circuit_breaker;
+mod config;
+mod ecs_log;
+mod encryption;
+mod expiry;
+mod fallback_file;
+mod hal;
+mod in_flight;
+mod metrics;
+#[cfg(test)]
+mod mock_airtable;
+mod near_rpc;
+mod nonce_persist;
+mod ownership;
+mod provider_health;
+mod quota;
+mod rate_limit;
+mod redact;
+mod registration_link;
+mod rolling_stats;
+mod routes;
+mod schema_drift;
+mod seen_events;
+mod signing;
+mod timeline;
+mod tx_proof;
+mod view_pool;
+mod wallet;
 
 struct AppState {
-    airtable_api_key: String,
+    config: config::Config,
+    started_at: std::time::Instant,
+    in_flight_lookups: in_flight::InFlightLookups<(near_account_id::AccountId, String)>,
+    /// Origins allowed to make cross-origin requests; any origin is allowed when empty.
+    cors_allowed_origins: std::sync::RwLock<Vec<HeaderValue>>,
+    airtable_client: std::sync::Arc<airtable_client::AirtableHttpClient>,
+    /// Lists the KYC records, backed by the `airtable_client` outside of tests.
+    airtable_provider: std::sync::Arc<dyn airtable_client::AirtableProvider>,
+    airtable_stats: rolling_stats::RollingStats,
+    /// Circuit breakers by [`circuit_breaker::current_circuit`].
+    airtable_circuit_breakers:
+        std::collections::HashMap<&'static str, circuit_breaker::CircuitBreaker>,
+    airtable_request_permits: tokio::sync::Semaphore,
+    airtable_quota: quota::AirtableQuota,
+    airtable_view_pool: view_pool::ViewRotationPool,
+    metrics: metrics::Metrics,
+    provider_health: provider_health::ProviderHealth,
+    kyc_cache: cache::KycCache,
+    stale_kyc_cache: cache::StaleKycCache,
+    /// Statuses of the `FALLBACK_STATIC_FILE`, served while the circuit breaker is open.
+    fallback_statuses: fallback_file::FallbackStatuses,
+    leaderboard_cache: cache::LeaderboardCache,
+    refresh_rate_limiter: rate_limit::RateLimiter<near_account_id::AccountId>,
+    /// Batch ownership verifications by requester IP.
+    ownership_batch_rate_limiter: rate_limit::RateLimiter<String>,
+    /// Issued ownership nonces by requester IP.
+    nonce_rate_limiter: rate_limit::RateLimiter<String>,
+    seen_webhook_events: seen_events::SeenEvents,
+    batch_jobs: batch_jobs::BatchJobs,
+    ownership_nonces: ownership::Nonces,
+    timeline: timeline::Timeline,
+    deletion_log: audit::DeletionLog,
+    webhook_notifier: expiry::WebhookNotifier,
+    near_rpc_client: near_rpc::NearRpcClient,
+    /// Encrypts the personal data kept on disk, when a `FIELD_ENCRYPTION_KEY` is set.
+    field_encryptor: Option<encryption::FieldEncryptor>,
+}
+
+impl AppState {
+    fn builder() -> AppStateBuilder {
+        AppStateBuilder::default()
+    }
+
+    /// Circuit breaker of the current task's circuit.
+    fn airtable_circuit_breaker(&self) -> &circuit_breaker::CircuitBreaker {
+        &self.airtable_circuit_breakers[circuit_breaker::current_circuit()]
+    }
+}
+
+/// Builds [`AppState`], deriving the runtime components from the config. A minimal state (e.g. for
+/// tests) needs nothing but the config defaults:
+///
+/// ```ignore
+/// let app_state = AppState::builder()
+///     .with_config(config::Config {
+///         airtable_base_url: "http://127.0.0.1:8080".to_string(),
+///         ..Default::default()
+///     })
+///     .build();
+/// ```
+#[derive(Default)]
+struct AppStateBuilder {
+    config: config::Config,
+    airtable_provider: Option<std::sync::Arc<dyn airtable_client::AirtableProvider>>,
+}
+
+impl AppStateBuilder {
+    fn with_config(mut self, config: config::Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    #[cfg(test)]
+    fn with_airtable_provider(
+        mut self,
+        airtable_provider: std::sync::Arc<dyn airtable_client::AirtableProvider>,
+    ) -> Self {
+        self.airtable_provider = Some(airtable_provider);
+        self
+    }
+
+    fn build(self) -> std::sync::Arc<AppState> {
+        let config = self.config;
+        let airtable_client =
+            std::sync::Arc::new(airtable_client::AirtableHttpClient::new(&config));
+        std::sync::Arc::new(AppState {
+            airtable_provider: self
+                .airtable_provider
+                .unwrap_or_else(|| airtable_client.clone()),
+            started_at: std::time::Instant::now(),
+            in_flight_lookups: Default::default(),
+            cors_allowed_origins: std::sync::RwLock::new(config.cors_allowed_origins.clone()),
+            airtable_client,
+            airtable_stats: Default::default(),
+            airtable_request_permits: tokio::sync::Semaphore::new(
+                config.airtable_max_concurrent_requests,
+            ),
+            airtable_quota: quota::AirtableQuota::new(config.airtable_monthly_quota),
+            airtable_view_pool: view_pool::ViewRotationPool::new(config.airtable_view_pool.clone()),
+            metrics: Default::default(),
+            provider_health: Default::default(),
+            kyc_cache: cache::KycCache::new(std::time::Duration::from_secs(
+                config.kyc_cache_ttl_secs,
+            )),
+            seen_webhook_events: seen_events::SeenEvents::new(MAX_SEEN_WEBHOOK_EVENTS),
+            batch_jobs: Default::default(),
+            ownership_nonces: Default::default(),
+            timeline: Default::default(),
+            deletion_log: Default::default(),
+            webhook_notifier: Default::default(),
+            near_rpc_client: Default::default(),
+            field_encryptor: config
+                .field_encryption_key
+                .as_ref()
+                .map(encryption::FieldEncryptor::new),
+            stale_kyc_cache: cache::StaleKycCache::new(config.stale_kyc_cache_max_entries),
+            fallback_statuses: Default::default(),
+            leaderboard_cache: cache::LeaderboardCache::new(std::time::Duration::from_secs(
+                config.leaderboard_cache_ttl_secs,
+            )),
+            refresh_rate_limiter: rate_limit::RateLimiter::new(
+                MAX_REFRESHES_PER_MINUTE,
+                std::time::Duration::from_secs(60),
+            ),
+            ownership_batch_rate_limiter: rate_limit::RateLimiter::new(
+                MAX_OWNERSHIP_BATCHES_PER_MINUTE,
+                std::time::Duration::from_secs(60),
+            ),
+            nonce_rate_limiter: rate_limit::RateLimiter::new(
+                MAX_NONCES_PER_MINUTE,
+                std::time::Duration::from_secs(60),
+            ),
+            airtable_circuit_breakers: [
+                circuit_breaker::LOOKUP_CIRCUIT,
+                circuit_breaker::BATCH_CIRCUIT,
+            ]
+            .into_iter()
+            .map(|circuit| {
+                (
+                    circuit,
+                    circuit_breaker::CircuitBreaker::new(
+                        config.circuit_breaker_failure_threshold,
+                        std::time::Duration::from_secs(config.circuit_breaker_open_secs),
+                    ),
+                )
+            })
+            .collect(),
+            config,
+        })
+    }
 }
 
 /// Example response from Airtable API:
@@ -25,39 +209,144 @@ struct AppState {
 ///         "near_wallet": "frol.near",
 ///         "status": "pending" / "rejected" / "approved",
 ///         "approval_standing": "" / "active" / "expired",
+///         "chain": "" / "NEAR" / "ETH" / "SOL",
+///         "wallet_address": "0x52908400098527886E0F7030069857D2E4169EE7",
 ///     }
 /// }]}
 /// ```
 #[derive(serde::Deserialize)]
 struct AirtableResponse {
     records: Vec<AirtableRecord>,
+    offset: Option<String>,
 }
 
 #[derive(serde::Deserialize)]
 struct AirtableRecord {
-    // id: String,
-    // #[serde(rename = "createdTime")]
-    // created_time: String,
+    id: String,
+    #[serde(
+        rename = "createdTime",
+        default,
+        deserialize_with = "deserialize_airtable_datetime"
+    )]
+    created_at: Option<chrono::DateTime<chrono::Utc>>,
     fields: AirtableFields,
 }
 
+impl AirtableRecord {
+    /// Whether the record is about the NEAR account itself, rather than a linked wallet on another
+    /// chain. Only these records count for the KYC status of the account.
+    fn is_near_record(&self) -> bool {
+        self.fields.chain() == Some(Chain::Near)
+    }
+}
+
 #[derive(serde::Deserialize)]
 struct AirtableFields {
-    // approval_date: String,
+    #[serde(default, deserialize_with = "deserialize_airtable_datetime")]
+    approval_date: Option<chrono::DateTime<chrono::Utc>>,
     // verification_type: String,
-    // near_wallet: String,
+    near_wallet: Option<String>,
+    /// Empty on the records created by the registration endpoint.
+    #[serde(default = "status_not_submitted")]
     status: KycStatus,
     #[serde(default = "approval_standing_inactive")]
     approval_standing: KycApprovalStanding,
+    /// IDs of the linked Contact records.
+    #[serde(rename = "Contact", default)]
+    contact: Vec<String>,
+    /// Chain of the `wallet_address`, records without one are about the NEAR account itself.
+    chain: Option<String>,
+    wallet_address: Option<String>,
+    /// Names of the other non-empty fields, which Airtable omits when empty.
+    #[serde(flatten)]
+    other_fields: std::collections::HashMap<String, serde::de::IgnoredAny>,
+}
+
+impl AirtableFields {
+    /// Whether the field is set, for the `REQUIRED_AIRTABLE_FIELDS` checks.
+    fn has_field(&self, name: &str) -> bool {
+        match name {
+            "approval_date" => self.approval_date.is_some(),
+            "near_wallet" => self.near_wallet.is_some(),
+            "status" | "approval_standing" => true,
+            "Contact" => !self.contact.is_empty(),
+            "chain" => self.chain.is_some(),
+            "wallet_address" => self.wallet_address.is_some(),
+            _ => self.other_fields.contains_key(name),
+        }
+    }
+
+    /// `None` for the chains the proxy does not support.
+    fn chain(&self) -> Option<Chain> {
+        match self.chain.as_deref().unwrap_or_default() {
+            "" | "NEAR" => Some(Chain::Near),
+            "ETH" => Some(Chain::Eth),
+            "SOL" => Some(Chain::Sol),
+            _ => None,
+        }
+    }
+
+    /// The `wallet_address` of the record, if it is a valid address on the chain.
+    fn chain_wallet_address(&self, chain: Chain) -> Option<wallet::WalletAddress> {
+        let wallet_address = self.wallet_address.as_deref()?;
+        match chain {
+            Chain::Near => wallet_address.parse().ok().map(wallet::WalletAddress::Near),
+            Chain::Eth => wallet_address.parse().ok().map(wallet::WalletAddress::Eth),
+            Chain::Sol => wallet_address.parse().ok().map(wallet::WalletAddress::Sol),
+        }
+    }
+}
+
+/// Airtable column names [`AirtableFields`] is read from.
+const AIRTABLE_FIELD_NAMES: [&str; 5] = [
+    "approval_date",
+    "near_wallet",
+    "status",
+    "approval_standing",
+    "Contact",
+];
+
+/// Airtable returns ISO 8601 timestamps, but date fields can be reconfigured by table editors, so
+/// an unparsable value is treated as missing instead of failing the whole response.
+fn deserialize_airtable_datetime<'de, D>(
+    deserializer: D,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(value.and_then(|value| {
+        chrono::DateTime::parse_from_rfc3339(&value)
+            .ok()
+            .map(|datetime| datetime.with_timezone(&chrono::Utc))
+    }))
 }
 
 #[derive(serde::Serialize)]
 struct KycResponse {
     account_id: near_account_id::AccountId,
     kyc_status: KycStatus,
+    network: config::NearNetwork,
 }
 
-#[derive(Copy, Clone, serde::Serialize, serde::Deserialize)]
+/// Version of the KYC status response layout, sent in `schema_version` and the
+/// `X-API-Schema-Version` header so clients can detect changes.
+const API_SCHEMA_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Media type clients accept to get [`KycResponseV2`] instead of [`KycResponse`].
+const KYC_V2_MEDIA_TYPE: &str = "application/vnd.kyc.v2+json";
+
+#[derive(serde::Serialize)]
+struct KycResponseV2 {
+    account_id: near_account_id::AccountId,
+    kyc_status: KycStatus,
+    network: config::NearNetwork,
+    /// Whether the KYC is approved, for clients which only gate on approval.
+    verified: bool,
+    checked_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 enum KycStatus {
     NotSubmitted,
@@ -70,6 +359,93 @@ enum KycStatus {
     Expired,
 }
 
+impl KycStatus {
+    /// How far the account got in the KYC process, used to check the status against a minimum.
+    fn rank(self) -> u8 {
+        match self {
+            KycStatus::Rejected => 0,
+            KycStatus::NotSubmitted => 1,
+            KycStatus::Expired => 2,
+            KycStatus::Pending => 3,
+            KycStatus::Approved => 4,
+        }
+    }
+
+    /// How long clients may cache a response with this status. Only `Pending` is expected to
+    /// change soon, so it is cached for a short time.
+    fn cache_max_age_secs(self) -> u64 {
+        match self {
+            KycStatus::Approved | KycStatus::Rejected => 3600,
+            KycStatus::Pending => 60,
+            KycStatus::NotSubmitted | KycStatus::Expired => DEFAULT_CACHE_MAX_AGE_SECS,
+        }
+    }
+}
+
+const DEFAULT_CACHE_MAX_AGE_SECS: u64 = 300;
+
+/// HTTP caching headers for a response reporting the given KYC status. With `ENABLE_HTTP_CACHE`,
+/// the `Cache-Control` header targets shared caches, see [`cache_control_header`].
+fn kyc_cache_headers(
+    kyc_status: KycStatus,
+    enable_http_cache: bool,
+) -> [(axum::http::HeaderName, HeaderValue); 2] {
+    let cache_control = if enable_http_cache {
+        cache_control_header(kyc_status)
+    } else {
+        default_cache_control_header(kyc_status)
+    };
+    [
+        (axum::http::header::CACHE_CONTROL, cache_control),
+        (
+            axum::http::header::VARY,
+            HeaderValue::from_static("Accept-Encoding, Accept"),
+        ),
+    ]
+}
+
+/// `Cache-Control` header for reverse proxies and CDNs: approvals are cached for longer by the
+/// shared caches, while pending statuses, which are expected to change soon, are not cached at
+/// all.
+fn cache_control_header(kyc_status: KycStatus) -> HeaderValue {
+    match kyc_status {
+        KycStatus::Approved => HeaderValue::from_static("public, max-age=3600, s-maxage=7200"),
+        KycStatus::Pending => HeaderValue::from_static("private, no-store"),
+        KycStatus::NotSubmitted | KycStatus::Rejected | KycStatus::Expired => {
+            default_cache_control_header(kyc_status)
+        }
+    }
+}
+
+fn default_cache_control_header(kyc_status: KycStatus) -> HeaderValue {
+    HeaderValue::from_str(&format!(
+        "public, max-age={}, stale-while-revalidate=30",
+        kyc_status.cache_max_age_secs()
+    ))
+    .expect("the header value is valid")
+}
+
+impl std::str::FromStr for KycStatus {
+    type Err = serde_json::Error;
+
+    /// Parses the status as it is serialized, e.g. `APPROVED`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(value.to_string()))
+    }
+}
+
+impl PartialOrd for KycStatus {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KycStatus {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
 #[derive(Copy, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 enum KycApprovalStanding {
@@ -78,98 +454,3664 @@ enum KycApprovalStanding {
     Expired,
 }
 
+fn status_not_submitted() -> KycStatus {
+    KycStatus::NotSubmitted
+}
+
 fn approval_standing_inactive() -> KycApprovalStanding {
     KycApprovalStanding::Expired
 }
 
+#[derive(Clone, Debug)]
 enum KycError {
     DatabaseError,
     DeserializationError,
+    Unauthorized,
+    AccountAlreadyRegistered,
+    InvalidInput(String),
+    /// Wallet addresses of a batch request which do not parse.
+    InvalidAccountIds(Vec<String>),
+    TooManyRequests,
+    ServiceUnavailable,
+    NearRpcError,
+    /// A lazily initialized component could not be initialized, named as in the response.
+    DependencyUnavailable(&'static str),
+}
+
+impl KycError {
+    fn status_and_message(self) -> (axum::http::StatusCode, String) {
+        match self {
+            KycError::DatabaseError => (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            ),
+            KycError::DeserializationError => (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "Deserialization error".to_string(),
+            ),
+            KycError::Unauthorized => (
+                axum::http::StatusCode::UNAUTHORIZED,
+                "Unauthorized".to_string(),
+            ),
+            KycError::AccountAlreadyRegistered => (
+                axum::http::StatusCode::CONFLICT,
+                "Account is already registered".to_string(),
+            ),
+            KycError::InvalidInput(message) => (axum::http::StatusCode::BAD_REQUEST, message),
+            KycError::InvalidAccountIds(account_ids) => (
+                axum::http::StatusCode::BAD_REQUEST,
+                format!("Invalid account IDs: {}", account_ids.join(", ")),
+            ),
+            KycError::TooManyRequests => (
+                axum::http::StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests".to_string(),
+            ),
+            KycError::ServiceUnavailable => (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                "Airtable is temporarily unavailable".to_string(),
+            ),
+            KycError::NearRpcError => (
+                axum::http::StatusCode::BAD_GATEWAY,
+                "NEAR RPC error".to_string(),
+            ),
+            KycError::DependencyUnavailable(dependency) => (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                format!("{dependency} is unavailable"),
+            ),
+        }
+    }
 }
 
 impl axum::response::IntoResponse for KycError {
     fn into_response(self) -> axum::response::Response {
-        let body = match self {
-            KycError::DatabaseError => "Database error".to_string(),
-            KycError::DeserializationError => "Deserialization error".to_string(),
-        };
-
+        if let KycError::InvalidAccountIds(invalid_account_ids) = self {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error_code": "INVALID_INPUT",
+                    "invalid_account_ids": invalid_account_ids,
+                })),
+            )
+                .into_response();
+        }
+        if let KycError::DependencyUnavailable(dependency) = self {
+            return (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error_code": "DEPENDENCY_UNAVAILABLE",
+                    "dependency": dependency,
+                })),
+            )
+                .into_response();
+        }
         // its often easiest to implement `IntoResponse` by calling other implementations
-        (axum::http::StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+        self.status_and_message().into_response()
     }
 }
 
-async fn get_account_kyc_status(
-    Path(account_id): Path<near_account_id::AccountId>,
-    State(state): State<std::sync::Arc<AppState>>,
-) -> Result<Json<KycResponse>, KycError> {
-    let body: AirtableResponse = reqwest::Client::new()
-        .get("https://api.airtable.com/v0/appjaTXAImNymlY6T/devhub_kyc")
-        .query(&[
-            ("maxRecords", "5"),
-            ("view", "Grid view"),
+const AIRTABLE_TABLE: &str = "devhub_kyc";
+const AIRTABLE_CONTACTS_TABLE: &str = "Contacts";
+
+async fn fetch_airtable_records<T: serde::de::DeserializeOwned>(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+) -> Result<T, KycError> {
+    query_airtable(
+        state,
+        &[
+            ("maxRecords", &state.config.airtable_max_records.to_string()),
+            ("view", view),
             (
                 "filterByFormula",
-                &format!("{{near_wallet}}='{account_id}'"),
+                &account_id::airtable_filter_formula(
+                    account_id,
+                    state.config.airtable_filter_template.as_deref(),
+                ),
             ),
-        ])
-        .header(
-            "Authorization",
-            format!("Bearer {}", state.airtable_api_key),
-        )
-        .send()
-        .await
-        .map_err(|_| KycError::DatabaseError)?
-        .json()
+        ],
+    )
+    .await
+}
+
+/// Whether the formula is too long for the query string, as some proxies reject long URLs.
+fn should_use_post_filter(formula: &str, max_url_length: usize) -> bool {
+    percent_encoding::utf8_percent_encode(formula, percent_encoding::NON_ALPHANUMERIC)
+        .map(str::len)
+        .sum::<usize>()
+        > max_url_length
+}
+
+async fn query_airtable<T: serde::de::DeserializeOwned>(
+    state: &AppState,
+    query: &[(&str, &str)],
+) -> Result<T, KycError> {
+    let body = state.airtable_provider.query_records(state, query).await?;
+    serde_json::from_value(body).map_err(|_| KycError::DeserializationError)
+}
+
+/// Streams the records matching the formula, following Airtable pagination. The next page is only
+/// fetched once the records of the previous one are consumed.
+fn stream_airtable_records<'a>(
+    state: &'a AppState,
+    formula: &'a str,
+    max_records: Option<u32>,
+) -> impl futures_util::Stream<Item = Result<AirtableRecord, KycError>> + 'a {
+    stream_airtable_view_records(
+        state,
+        &state.config.airtable_view_name,
+        formula,
+        max_records,
+    )
+}
+
+/// Same as [`stream_airtable_records`], but reads the records of the given view.
+fn stream_airtable_view_records<'a>(
+    state: &'a AppState,
+    view: &'a str,
+    formula: &'a str,
+    max_records: Option<u32>,
+) -> impl futures_util::Stream<Item = Result<AirtableRecord, KycError>> + 'a {
+    async_stream::try_stream! {
+        let max_records = max_records.map(|max_records| max_records.to_string());
+        let mut offset: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("view", view),
+                ("filterByFormula", formula),
+            ];
+            if let Some(max_records) = &max_records {
+                query.push(("maxRecords", max_records));
+            }
+            if let Some(offset) = &offset {
+                query.push(("offset", offset));
+            }
+            let page: AirtableResponse = query_airtable(state, &query).await?;
+            for record in page.records {
+                yield record;
+            }
+
+            offset = page.offset;
+            if offset.is_none() {
+                break;
+            }
+        }
+    }
+}
+
+/// Fetches all the records matching the formula into memory.
+async fn fetch_all_airtable_records(
+    state: &AppState,
+    formula: &str,
+    max_records: Option<u32>,
+) -> Result<Vec<AirtableRecord>, KycError> {
+    futures_util::TryStreamExt::try_collect(stream_airtable_records(state, formula, max_records))
         .await
-        .map_err(|_err| { dbg!(_err); KycError::DeserializationError })?;
+}
 
-    Ok(Json(KycResponse {
-        account_id,
-        kyc_status: if let Some(active_record) = body
-            .records
-            .iter()
-            .filter(|record| matches!(record.fields.approval_standing, KycApprovalStanding::Active))
-            .next()
+/// Sends the request to Airtable, keeping track of how the Airtable API performs and backing off
+/// through the circuit breaker while it keeps failing.
+async fn send_airtable_request(
+    state: &AppState,
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, KycError> {
+    if !state.airtable_circuit_breaker().allows_requests() {
+        return Err(KycError::ServiceUnavailable);
+    }
+
+    // Large batches would otherwise open enough concurrent connections to hit the Airtable rate
+    // limit
+    let _permit = match state.airtable_request_permits.try_acquire() {
+        Ok(permit) => permit,
+        Err(_) => {
+            tracing::warn!(
+                max_concurrent_requests = state.config.airtable_max_concurrent_requests,
+                "All Airtable request permits are in use, waiting for a permit"
+            );
+            state
+                .airtable_request_permits
+                .acquire()
+                .await
+                .expect("the semaphore is never closed")
+        }
+    };
+
+    if state.airtable_quota.record_call() {
+        tracing::warn!(
+            monthly_quota = state.config.airtable_monthly_quota,
+            "Airtable monthly quota is used up, the circuit breakers are held open until the quota is reset"
+        );
+        for circuit_breaker in state.airtable_circuit_breakers.values() {
+            circuit_breaker.hold_open();
+        }
+    }
+
+    let (client, request) = request.build_split();
+    let request = request.map_err(|_| KycError::DatabaseError)?;
+    let previous_key_request = state.airtable_client.with_previous_api_key(&request);
+    let started_at = std::time::Instant::now();
+    let mut response = client.execute(request).await;
+    if let (Ok(rejected_response), Some(previous_key_request)) = (&response, previous_key_request) {
+        if rejected_response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            tracing::warn!("Airtable rejected the rotated API key, retrying with the previous one during the grace period");
+            response = client.execute(previous_key_request).await;
+        }
+    }
+    let duration = started_at.elapsed();
+    state.airtable_stats.record(
+        duration,
+        matches!(&response, Ok(response) if response.status().is_success()),
+    );
+    state.metrics.observe_airtable_response(
+        response.as_ref().ok().map(|response| response.status()),
+        duration,
+    );
+
+    match &response {
+        Ok(response)
+            if !response.status().is_server_error()
+                && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS =>
         {
-            active_record.fields.status
+            state.airtable_circuit_breaker().record_success()
+        }
+        _ => state.airtable_circuit_breaker().record_failure(),
+    }
+
+    response.map_err(|_| KycError::DatabaseError)
+}
+
+/// KYC status of a single record. Registered records which were never submitted have no approval
+/// standing to expire.
+fn record_kyc_status(record: &AirtableRecord) -> KycStatus {
+    if record.fields.status == KycStatus::NotSubmitted {
+        KycStatus::NotSubmitted
+    } else if let KycApprovalStanding::Expired = record.fields.approval_standing {
+        KycStatus::Expired
+    } else {
+        record.fields.status
+    }
+}
+
+impl config::TieBreakingPolicy {
+    /// Whether the candidate record should decide the KYC status instead of the selected one.
+    /// Records which are not preferred in either direction keep the earlier one selected.
+    fn prefers(self, candidate: &AirtableRecord, selected: &AirtableRecord) -> bool {
+        let is_active = |record: &AirtableRecord| {
+            matches!(record.fields.approval_standing, KycApprovalStanding::Active)
+        };
+        match self {
+            Self::PreferVerified => {
+                !is_active(selected)
+                    && (is_active(candidate) || candidate.created_at > selected.created_at)
+            }
+            Self::MostRecent => candidate.created_at > selected.created_at,
+            Self::Strictest => record_kyc_status(candidate) < record_kyc_status(selected),
+            Self::Lenient => record_kyc_status(candidate) > record_kyc_status(selected),
+        }
+    }
+}
+
+/// Picks the KYC status of the account out of all its records according to the policy, returning
+/// the index of the record the status was taken from.
+///
+/// By default an active approval always wins, otherwise the most recently created submission is
+/// used.
+fn select_kyc_status(
+    records: &[AirtableRecord],
+    policy: config::TieBreakingPolicy,
+) -> (KycStatus, Option<usize>) {
+    let selected_index = (0..records.len()).reduce(|selected, candidate| {
+        if policy.prefers(&records[candidate], &records[selected]) {
+            candidate
         } else {
-            body.records
-                .first()
-                .map(|record| {
-                    if let KycApprovalStanding::Expired = record.fields.approval_standing {
-                        KycStatus::Expired
-                    } else {
-                        record.fields.status
-                    }
-                })
-                .unwrap_or(KycStatus::NotSubmitted)
-        },
-    }))
+            selected
+        }
+    });
+    match selected_index {
+        Some(index) => (record_kyc_status(&records[index]), Some(index)),
+        None => (KycStatus::NotSubmitted, None),
+    }
 }
 
-#[shuttle_runtime::main]
-async fn main(#[shuttle_runtime::Secrets] secret_store: SecretStore) -> shuttle_axum::ShuttleAxum {
-    let airtable_api_key = if let Some(airtable_api_key) = secret_store.get("AIRTABLE_API_KEY") {
-        airtable_api_key
+async fn lookup_kyc_status(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+) -> Result<KycStatus, KycError> {
+    if let Some(&kyc_status) = state.config.kyc_bypass_accounts.get(account_id) {
+        tracing::debug!(%account_id, ?kyc_status, "KYC bypass account was looked up");
+        return Ok(kyc_status);
+    }
+    let wallet_address = wallet::WalletAddress::from(account_id.clone());
+    if let Some(kyc_status) = state.kyc_cache.get(&wallet_address, view) {
+        return Ok(kyc_status);
+    }
+    let kyc_status = state
+        .in_flight_lookups
+        .get_or_fetch((account_id.clone(), view.to_string()), async {
+            lookup_airtable_kyc_status(state, account_id, view)
+                .await
+                .map(|lookup| lookup.kyc_status)
+        })
+        .await?;
+    store_kyc_status(state, account_id, view, kyc_status);
+    Ok(kyc_status)
+}
+
+/// KYC status of the account in Airtable, with the record it was taken from.
+struct AirtableKycLookup {
+    kyc_status: KycStatus,
+    record: Option<AirtableRecord>,
+    /// Fields of the record as Airtable returned them.
+    raw_fields: serde_json::Value,
+}
+
+/// Looks up the account in Airtable, bypassing the KYC cache and the bypass list.
+async fn lookup_airtable_kyc_status(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+) -> Result<AirtableKycLookup, KycError> {
+    // Lookups in the default view are spread over the view pool
+    let airtable_view = if view == state.config.airtable_view_name {
+        state.airtable_view_pool.pick().unwrap_or(view)
     } else {
-        return Err(anyhow!("AIRTABLE_API_KEY was not found").into());
+        view
     };
+    let mut raw_body: serde_json::Value =
+        fetch_airtable_records(state, account_id, airtable_view).await?;
+    let mut body = <AirtableResponse as serde::Deserialize>::deserialize(&raw_body)
+        .map_err(|_| KycError::DeserializationError)?;
+    state
+        .airtable_view_pool
+        .record(airtable_view, body.records.is_empty());
+    body.records.retain(AirtableRecord::is_near_record);
+    body.records.retain(|record| {
+        let missing_fields = state
+            .config
+            .required_airtable_fields
+            .iter()
+            .filter(|field| !record.fields.has_field(field))
+            .collect::<Vec<_>>();
+        if !missing_fields.is_empty() {
+            tracing::warn!(%account_id, record_id = record.id, ?missing_fields, "Skipping an Airtable record without the required fields");
+        }
+        missing_fields.is_empty()
+    });
+    if state.config.airtable_follow_linked_records {
+        apply_contact_approval_standing(state, &mut body.records).await;
+    }
+    let (kyc_status, record_index) =
+        select_kyc_status(&body.records, state.config.tie_breaking_policy);
+    let record = record_index.map(|index| body.records.swap_remove(index));
+    if let Some(record) = &record {
+        match kyc_status {
+            // The expired record is already resubmitted when its status is pending
+            KycStatus::Expired
+                if state.config.auto_resubmit_on_expiry
+                    && record.fields.status != KycStatus::Pending =>
+            {
+                resubmit_expired_record(state, account_id, &record.id).await;
+            }
+            KycStatus::Approved
+                if state.config.airtable_writeback && record.fields.approval_date.is_none() =>
+            {
+                write_back_approval_date(state, account_id, &record.id).await;
+            }
+            _ => {}
+        }
+    }
+    let raw_fields = record
+        .as_ref()
+        .and_then(|record| {
+            raw_body["records"]
+                .as_array_mut()?
+                .iter_mut()
+                .find(|raw_record| raw_record["id"] == record.id.as_str())
+        })
+        .map(|raw_record| raw_record["fields"].take())
+        .unwrap_or_default();
+    Ok(AirtableKycLookup {
+        kyc_status,
+        record,
+        raw_fields,
+    })
+}
 
-    let app_state = std::sync::Arc::new(AppState { airtable_api_key });
+/// Webhook event about a status which changed since the last lookup.
+#[derive(serde::Serialize)]
+struct StatusChangedEvent<'a> {
+    event: &'static str,
+    account_id: &'a near_account_id::AccountId,
+    from_status: KycStatus,
+    to_status: KycStatus,
+    changed_at: chrono::DateTime<chrono::Utc>,
+}
 
-    let router = Router::new()
-        .route("/kyc/:account_id", get(get_account_kyc_status))
-        .layer(
-            ServiceBuilder::new().layer(
-                CorsLayer::new()
-                    // allow `GET` and `POST` when accessing the resource
-                    .allow_methods([Method::GET, Method::POST])
-                    // allow requests from any origin
-                    .allow_origin(Any),
-            ),
+/// Caches the looked up status, recording it in the timeline and notifying the webhooks if it
+/// changed since the last lookup. Only the statuses of existing records are kept as the last known
+/// ones, so lookups of made up accounts cannot grow the stale cache.
+fn store_kyc_status(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+    kyc_status: KycStatus,
+) {
+    let wallet_address = wallet::WalletAddress::from(account_id.clone());
+    state.kyc_cache.insert(&wallet_address, view, kyc_status);
+    if let Some((old_kyc_status, _)) = state.stale_kyc_cache.get(&wallet_address, view) {
+        if old_kyc_status != kyc_status {
+            state.timeline.record(
+                account_id,
+                timeline::TimelineEvent::StatusChanged {
+                    from: old_kyc_status,
+                    to: kyc_status,
+                },
+            );
+            state.webhook_notifier.enqueue(
+                state,
+                account_id.as_str(),
+                &StatusChangedEvent {
+                    event: "kyc_status_changed",
+                    account_id,
+                    from_status: old_kyc_status,
+                    to_status: kyc_status,
+                    changed_at: chrono::Utc::now(),
+                },
+            );
+        }
+    }
+    if kyc_status == KycStatus::NotSubmitted {
+        state.stale_kyc_cache.remove(&wallet_address, view);
+    } else {
+        state
+            .stale_kyc_cache
+            .insert(&wallet_address, view, kyc_status);
+    }
+}
+
+/// Where a looked up KYC status comes from.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum KycStatusSource {
+    Airtable,
+    /// The last known status, as Airtable returned an unusable response.
+    Stale,
+    /// The `FALLBACK_STATIC_FILE`, as the circuit breaker is open and the status is not known.
+    FallbackFile,
+}
+
+/// Same as [`lookup_kyc_status`], but falls back to the last known status when Airtable returns
+/// an unusable response or the circuit breaker is open, and then to the `FALLBACK_STATIC_FILE`.
+async fn lookup_kyc_status_or_stale(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+) -> Result<(KycStatus, KycStatusSource), KycError> {
+    let err = match lookup_kyc_status(state, account_id, view).await {
+        Ok(kyc_status) => return Ok((kyc_status, KycStatusSource::Airtable)),
+        Err(err) => err,
+    };
+    let circuit_open = !state.airtable_circuit_breaker().allows_requests();
+    if !circuit_open
+        && !matches!(
+            err,
+            KycError::DatabaseError | KycError::DeserializationError
         )
-        .with_state(app_state);
+    {
+        return Err(err);
+    }
+
+    if let Some((kyc_status, looked_up_at)) =
+        state.stale_kyc_cache.get(&account_id.clone().into(), view)
+    {
+        tracing::warn!(
+            %account_id,
+            error = %err.status_and_message().1,
+            age_secs = looked_up_at.elapsed().as_secs(),
+            "Airtable lookup failed, serving the last known KYC status"
+        );
+        return Ok((kyc_status, KycStatusSource::Stale));
+    }
+    match state.fallback_statuses.get(account_id) {
+        Some(kyc_status) if circuit_open => {
+            tracing::warn!(
+                %account_id,
+                "Airtable is unavailable, serving the KYC status of the fallback file"
+            );
+            Ok((kyc_status, KycStatusSource::FallbackFile))
+        }
+        _ => Err(err),
+    }
+}
+
+/// Sets the status of the expired record back to pending, which makes the Airtable automations
+/// send the KYC link to the user again. The lookup still reports the status as expired, since the
+/// resubmission is not confirmed yet.
+async fn resubmit_expired_record(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    record_id: &str,
+) {
+    let result = update_airtable_record(
+        state,
+        record_id,
+        serde_json::json!({
+            "status": "pending",
+        }),
+    )
+    .await;
+    match result {
+        Ok(()) => tracing::info!(%account_id, record_id, "Expired KYC record was resubmitted"),
+        Err(err) => tracing::warn!(
+            %account_id,
+            record_id,
+            error = %err.status_and_message().1,
+            "Failed to resubmit expired KYC record"
+        ),
+    }
+}
+
+const APPROVAL_DATE_WRITEBACK_ATTEMPTS: u32 = 3;
+
+/// Fills in the missing approval date of an approved record, for KYC providers which do not set
+/// it themselves. Failures are only logged, the lookup result does not depend on the write-back.
+/// The lookup waits for the write-back, so the retries stop once the next one could not finish
+/// within `AIRTABLE_RETRY_BUDGET_MS` of the first attempt.
+async fn write_back_approval_date(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    record_id: &str,
+) {
+    let fields = serde_json::json!({
+        "approval_date": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+    });
+    let deadline = std::time::Instant::now()
+        + std::time::Duration::from_millis(state.config.airtable_retry_budget_ms);
+    for attempt in 1..=APPROVAL_DATE_WRITEBACK_ATTEMPTS {
+        match update_airtable_record(state, record_id, fields.clone()).await {
+            Ok(()) => {
+                tracing::info!(%account_id, record_id, "Approval date was written back to Airtable");
+                return;
+            }
+            Err(err) => {
+                tracing::warn!(
+                    %account_id,
+                    record_id,
+                    attempt,
+                    error = %err.status_and_message().1,
+                    "Failed to write back the approval date to Airtable"
+                );
+                if attempt == APPROVAL_DATE_WRITEBACK_ATTEMPTS {
+                    break;
+                }
+                let retry_at = std::time::Instant::now()
+                    + state.config.airtable_retry_strategy.next_delay(attempt);
+                if retry_at >= deadline {
+                    tracing::warn!(
+                        %account_id,
+                        record_id,
+                        "Approval date write-back retries exceeded the retry budget"
+                    );
+                    break;
+                }
+                tokio::time::sleep_until(retry_at.into()).await;
+            }
+        }
+    }
+}
+
+async fn update_airtable_record(
+    state: &AppState,
+    record_id: &str,
+    fields: serde_json::Value,
+) -> Result<(), KycError> {
+    send_airtable_request(
+        state,
+        state
+            .airtable_client
+            .request(
+                reqwest::Method::PATCH,
+                &format!("{AIRTABLE_TABLE}/{record_id}"),
+            )
+            .json(&serde_json::json!({ "fields": fields })),
+    )
+    .await?
+    .error_for_status()
+    .map_err(|_| KycError::DatabaseError)?;
+    Ok(())
+}
+
+#[derive(serde::Deserialize)]
+struct AirtableContactRecord {
+    fields: AirtableContactFields,
+}
+
+#[derive(serde::Deserialize)]
+struct AirtableContactFields {
+    #[serde(rename = "KYC Approval Standing")]
+    approval_standing: Option<KycApprovalStanding>,
+}
+
+/// The KYC approval may be tracked on the linked Contact record rather than on the wallet record,
+/// in which case the Contact approval standing takes precedence. The wallet record standing is
+/// kept when the Contact record cannot be fetched.
+async fn apply_contact_approval_standing(state: &AppState, records: &mut [AirtableRecord]) {
+    let mut contact_approval_standings = std::collections::HashMap::new();
+    for record in records {
+        let Some(contact_id) = record.fields.contact.first() else {
+            continue;
+        };
+        if !contact_approval_standings.contains_key(contact_id) {
+            let approval_standing = fetch_contact_approval_standing(state, contact_id)
+                .await
+                .inspect_err(|_| {
+                    tracing::warn!(%contact_id, "failed to fetch the linked Contact record");
+                })
+                .ok()
+                .flatten();
+            contact_approval_standings.insert(contact_id.clone(), approval_standing);
+        }
+        if let Some(approval_standing) = contact_approval_standings[contact_id] {
+            record.fields.approval_standing = approval_standing;
+        }
+    }
+}
+
+async fn fetch_contact_approval_standing(
+    state: &AppState,
+    contact_id: &str,
+) -> Result<Option<KycApprovalStanding>, KycError> {
+    let contact: AirtableContactRecord = send_airtable_request(
+        state,
+        state.airtable_client.request(
+            reqwest::Method::GET,
+            &format!("{AIRTABLE_CONTACTS_TABLE}/{contact_id}"),
+        ),
+    )
+    .await?
+    .error_for_status()
+    .map_err(|_| KycError::DatabaseError)?
+    .json()
+    .await
+    .map_err(|_| KycError::DeserializationError)?;
+    Ok(contact.fields.approval_standing)
+}
+
+#[derive(serde::Deserialize)]
+struct KycQuery {
+    /// Comma-separated list of the response fields to return, e.g. `account_id,kyc_status`.
+    fields: Option<String>,
+    /// Airtable view to look the account up in, one of `AIRTABLE_ALLOWED_VIEWS`.
+    view: Option<String>,
+    /// Recent transaction signed by the account, proving the requester controls it.
+    tx_id: Option<String>,
+    /// Also look up the wallets of the other chains, see [`get_all_chains_kyc_status`].
+    #[serde(default)]
+    all_chains: bool,
+}
+
+async fn get_account_kyc_status(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<KycQuery>,
+    headers: axum::http::HeaderMap,
+) -> Result<
+    (
+        [(axum::http::HeaderName, HeaderValue); 2],
+        [(axum::http::HeaderName, HeaderValue); 2],
+        axum::Extension<audit::AuditedLookup>,
+        Json<serde_json::Value>,
+    ),
+    KycError,
+> {
+    ensure_account_network(&state, &account_id)?;
+    match &query.tx_id {
+        Some(tx_id) => tx_proof::verify_tx_proof(&state, &account_id, tx_id).await?,
+        None if state.config.require_tx_proof => {
+            return Err(KycError::InvalidInput(
+                "A transaction proof (`tx_id`) is required".to_string(),
+            ))
+        }
+        None => {}
+    }
+
+    let view = match &query.view {
+        Some(view) if view != &state.config.airtable_view_name => {
+            if !state.config.airtable_allowed_views.contains(view) {
+                return Err(KycError::InvalidInput(format!(
+                    "View is not allowed: {view}"
+                )));
+            }
+            view
+        }
+        _ => &state.config.airtable_view_name,
+    };
+    if query.all_chains {
+        let response = get_all_chains_kyc_status(&state, &account_id, view).await?;
+        let kyc_status = response.overall;
+        let response = select_response_fields(
+            into_json_object(serde_json::to_value(response))?,
+            query.fields.as_deref(),
+        )?;
+        return Ok((
+            kyc_cache_headers(kyc_status, state.config.enable_http_cache),
+            kyc_response_headers("application/json"),
+            axum::Extension(audit::AuditedLookup {
+                account_id,
+                kyc_status,
+            }),
+            Json(response),
+        ));
+    }
+    let (kyc_status, source) = lookup_kyc_status_or_stale(&state, &account_id, view).await?;
+
+    let v2 = headers
+        .get_all(axum::http::header::ACCEPT)
+        .iter()
+        .filter_map(|accept| accept.to_str().ok())
+        .any(|accept| accept.contains(KYC_V2_MEDIA_TYPE));
+    let response = if v2 {
+        serde_json::to_value(KycResponseV2 {
+            account_id: account_id.clone(),
+            kyc_status,
+            network: state.config.near_network,
+            verified: kyc_status == KycStatus::Approved,
+            checked_at: chrono::Utc::now(),
+        })
+    } else {
+        serde_json::to_value(KycResponse {
+            account_id: account_id.clone(),
+            kyc_status,
+            network: state.config.near_network,
+        })
+    };
+    let mut response = into_json_object(response)?;
+    response.insert("schema_version".to_string(), API_SCHEMA_VERSION.into());
+    match source {
+        KycStatusSource::Airtable => {}
+        KycStatusSource::Stale => {
+            response.insert("stale".to_string(), true.into());
+        }
+        KycStatusSource::FallbackFile => {
+            response.insert("source".to_string(), "fallback_file".into());
+        }
+    }
+    if state.config.kyc_bypass_accounts.contains_key(&account_id) {
+        response.insert("bypass".to_string(), true.into());
+    }
+    let mut response = select_response_fields(response, query.fields.as_deref())?;
+    let content_type = match state.config.response_format {
+        config::ResponseFormat::Json if v2 => KYC_V2_MEDIA_TYPE,
+        config::ResponseFormat::Json => "application/json",
+        config::ResponseFormat::Hal => {
+            response = serde_json::to_value(hal::HalResponse::kyc_status(&account_id, response))
+                .expect("the response is serializable");
+            "application/hal+json"
+        }
+    };
+    Ok((
+        kyc_cache_headers(kyc_status, state.config.enable_http_cache),
+        kyc_response_headers(content_type),
+        axum::Extension(audit::AuditedLookup {
+            account_id,
+            kyc_status,
+        }),
+        Json(response),
+    ))
+}
+
+fn kyc_response_headers(content_type: &'static str) -> [(axum::http::HeaderName, HeaderValue); 2] {
+    [
+        (
+            axum::http::header::CONTENT_TYPE,
+            HeaderValue::from_static(content_type),
+        ),
+        (
+            axum::http::HeaderName::from_static("x-api-schema-version"),
+            HeaderValue::from_static(API_SCHEMA_VERSION),
+        ),
+    ]
+}
+
+fn into_json_object(
+    response: serde_json::Result<serde_json::Value>,
+) -> Result<serde_json::Map<String, serde_json::Value>, KycError> {
+    match response.map_err(|_| KycError::DeserializationError)? {
+        serde_json::Value::Object(response) => Ok(response),
+        _ => unreachable!("responses are serialized as JSON objects"),
+    }
+}
+
+/// Status of the account on one chain of a `?all_chains=true` lookup.
+#[derive(serde::Serialize)]
+struct ChainKycStatus {
+    /// Wallet of the record the status was taken from, not set for the looked up NEAR account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wallet_address: Option<wallet::WalletAddress>,
+    kyc_status: KycStatus,
+}
+
+#[derive(serde::Serialize)]
+struct AllChainsKycResponse {
+    account_id: near_account_id::AccountId,
+    near: ChainKycStatus,
+    eth: ChainKycStatus,
+    sol: ChainKycStatus,
+    /// Picked out of the chains according to the `ALL_CHAINS_POLICY`.
+    overall: KycStatus,
+}
+
+impl config::AllChainsPolicy {
+    /// The chains without records do not count, so an account is not held back by the chains it
+    /// never used.
+    fn overall_kyc_status(self, kyc_statuses: &[KycStatus]) -> KycStatus {
+        let submitted = kyc_statuses
+            .iter()
+            .copied()
+            .filter(|kyc_status| *kyc_status != KycStatus::NotSubmitted);
+        match self {
+            Self::MostPermissive => submitted.max(),
+            Self::MostRestrictive => submitted.min(),
+        }
+        .unwrap_or(KycStatus::NotSubmitted)
+    }
+}
+
+/// Looks up the NEAR account along with the ETH and SOL wallets linked to it, which are the
+/// records with the account in `near_wallet` and the chain in the `chain` field. The chains are
+/// looked up concurrently; only the NEAR status is cached.
+async fn get_all_chains_kyc_status(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+) -> Result<AllChainsKycResponse, KycError> {
+    let (near, eth, sol) = futures_util::future::join3(
+        lookup_kyc_status_or_stale(state, account_id, view),
+        lookup_chain_kyc_status(state, account_id, view, Chain::Eth),
+        lookup_chain_kyc_status(state, account_id, view, Chain::Sol),
+    )
+    .await;
+    let near = ChainKycStatus {
+        wallet_address: None,
+        kyc_status: near?.0,
+    };
+    let (eth, sol) = (eth?, sol?);
+    Ok(AllChainsKycResponse {
+        overall: state.config.all_chains_policy.overall_kyc_status(&[
+            near.kyc_status,
+            eth.kyc_status,
+            sol.kyc_status,
+        ]),
+        account_id: account_id.clone(),
+        near,
+        eth,
+        sol,
+    })
+}
+
+async fn lookup_chain_kyc_status(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+    chain: Chain,
+) -> Result<ChainKycStatus, KycError> {
+    let records = fetch_chain_records(state, account_id, view, chain).await?;
+    let (kyc_status, record_index) = select_kyc_status(&records, state.config.tie_breaking_policy);
+    Ok(ChainKycStatus {
+        wallet_address: record_index
+            .and_then(|index| records[index].fields.chain_wallet_address(chain)),
+        kyc_status,
+    })
+}
+
+/// Records of the account's wallets on the chain.
+async fn fetch_chain_records(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+    view: &str,
+    chain: Chain,
+) -> Result<Vec<AirtableRecord>, KycError> {
+    let formula = format!(
+        "AND({}, {{chain}}='{}')",
+        account_id::airtable_filter_formula(
+            account_id,
+            state.config.airtable_filter_template.as_deref()
+        ),
+        chain.airtable_value()
+    );
+    let body: AirtableResponse = query_airtable(
+        state,
+        &[
+            ("maxRecords", &state.config.airtable_max_records.to_string()),
+            ("view", view),
+            ("filterByFormula", &formula),
+        ],
+    )
+    .await?;
+    Ok(body.records)
+}
+
+/// Serves an SVG badge of the KYC status, e.g. for project READMEs.
+async fn get_account_kyc_badge(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<axum::response::Response, KycError> {
+    ensure_account_network(&state, &account_id)?;
+    let (kyc_status, _) =
+        lookup_kyc_status_or_stale(&state, &account_id, &state.config.airtable_view_name).await?;
+
+    let badge = badge::render_badge(kyc_status);
+    let etag = badge::badge_etag(&badge);
+    let response_headers = [
+        (axum::http::header::CACHE_CONTROL, "public, max-age=3600"),
+        (axum::http::header::ETAG, etag.as_str()),
+    ];
+    let not_modified = headers
+        .get(axum::http::header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+    if not_modified {
+        return Ok((axum::http::StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+    Ok((
+        response_headers,
+        [(axum::http::header::CONTENT_TYPE, "image/svg+xml")],
+        badge,
+    )
+        .into_response())
+}
+
+/// Response fields which are only sent for some lookups, but can always be requested.
+const OPTIONAL_RESPONSE_FIELDS: [&str; 3] = ["stale", "source", "bypass"];
+
+/// Keeps only the requested top-level fields of the response, including `schema_version` and the
+/// optional fields.
+fn select_response_fields(
+    mut response: serde_json::Map<String, serde_json::Value>,
+    fields: Option<&str>,
+) -> Result<serde_json::Value, KycError> {
+    let Some(fields) = fields else {
+        return Ok(response.into());
+    };
+
+    let fields = fields.split(',').map(str::trim).collect::<Vec<_>>();
+    if let Some(unknown_field) = fields
+        .iter()
+        .find(|field| !response.contains_key(**field) && !OPTIONAL_RESPONSE_FIELDS.contains(field))
+    {
+        return Err(KycError::InvalidInput(format!(
+            "Unknown response field: {unknown_field}"
+        )));
+    }
+    response.retain(|key, _| fields.contains(&key.as_str()));
+    Ok(response.into())
+}
+
+#[derive(serde::Deserialize)]
+struct KycCheckQuery {
+    min_status: Option<KycStatus>,
+}
+
+#[derive(serde::Serialize)]
+struct KycCheckResponse {
+    cleared: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kyc_status: Option<KycStatus>,
+}
+
+/// Same check as [`check_account_kyc_status`], answered with the status code alone (`200` or
+/// `403`) for authorization subrequests, e.g. of nginx `auth_request`.
+async fn check_account_kyc_status_exists(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<KycCheckQuery>,
+) -> Result<axum::http::StatusCode, KycError> {
+    ensure_account_network(&state, &account_id)?;
+
+    let min_status = query.min_status.unwrap_or(KycStatus::Approved);
+    let kyc_status =
+        lookup_kyc_status(&state, &account_id, &state.config.airtable_view_name).await?;
+
+    Ok(if kyc_status >= min_status {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::FORBIDDEN
+    })
+}
+
+/// Answers whether the account KYC status is at least `min_status` (`APPROVED` by default).
+async fn check_account_kyc_status(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<KycCheckQuery>,
+) -> Result<(axum::http::StatusCode, Json<KycCheckResponse>), KycError> {
+    ensure_account_network(&state, &account_id)?;
 
-    Ok(router.into())
+    let min_status = query.min_status.unwrap_or(KycStatus::Approved);
+    let kyc_status =
+        lookup_kyc_status(&state, &account_id, &state.config.airtable_view_name).await?;
+
+    Ok(if kyc_status >= min_status {
+        (
+            axum::http::StatusCode::OK,
+            Json(KycCheckResponse {
+                cleared: true,
+                kyc_status: None,
+            }),
+        )
+    } else {
+        (
+            axum::http::StatusCode::FORBIDDEN,
+            Json(KycCheckResponse {
+                cleared: false,
+                kyc_status: Some(kyc_status),
+            }),
+        )
+    })
+}
+
+#[derive(serde::Serialize)]
+struct AdminKycResponse {
+    #[serde(flatten)]
+    kyc: KycResponse,
+    airtable_record_id: Option<String>,
+    created_at: Option<chrono::DateTime<chrono::Utc>>,
+    raw_fields: serde_json::Value,
+}
+
+/// Same lookup as [`get_account_kyc_status`], but also exposes the Airtable record the status
+/// was taken from, so support staff can open it directly in the Airtable UI. The KYC cache is
+/// skipped, so the response always reflects the current record.
+async fn get_admin_account_kyc_status(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+) -> Result<Json<AdminKycResponse>, KycError> {
+    let view = &state.config.airtable_view_name;
+    let lookup = if let Some(&kyc_status) = state.config.kyc_bypass_accounts.get(&account_id) {
+        AirtableKycLookup {
+            kyc_status,
+            record: None,
+            raw_fields: serde_json::Value::Null,
+        }
+    } else {
+        let lookup = lookup_airtable_kyc_status(&state, &account_id, view).await?;
+        store_kyc_status(&state, &account_id, view, lookup.kyc_status);
+        lookup
+    };
+
+    Ok(Json(AdminKycResponse {
+        kyc: KycResponse {
+            account_id,
+            kyc_status: lookup.kyc_status,
+            network: state.config.near_network,
+        },
+        airtable_record_id: lookup.record.as_ref().map(|record| record.id.clone()),
+        created_at: lookup.record.and_then(|record| record.created_at),
+        raw_fields: lookup.raw_fields,
+    }))
+}
+
+#[derive(serde::Serialize)]
+struct ComplianceReport {
+    account_id: near_account_id::AccountId,
+    as_of: chrono::DateTime<chrono::Utc>,
+    data_sources: ComplianceDataSources,
+}
+
+#[derive(serde::Serialize)]
+struct ComplianceDataSources {
+    /// Raw Airtable records of the account.
+    airtable: serde_json::Value,
+    cache: ComplianceCacheEntries,
+    audit_log: Vec<timeline::TimelineEntry>,
+    /// Status configured in `KYC_BYPASS_ACCOUNTS`, which takes precedence over Airtable.
+    kyc_bypass_status: Option<KycStatus>,
+}
+
+#[derive(serde::Serialize)]
+struct ComplianceCacheEntries {
+    kyc_cache: Vec<cache::CacheEntry>,
+    stale_kyc_cache: Vec<cache::CacheEntry>,
+}
+
+/// Exports all the data held about the account, for data subject access requests.
+async fn get_account_compliance_report(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+) -> Result<Json<ComplianceReport>, KycError> {
+    let mut raw_body: serde_json::Value =
+        fetch_airtable_records(&state, &account_id, &state.config.airtable_view_name).await?;
+    let wallet_address = wallet::WalletAddress::from(account_id.clone());
+
+    Ok(Json(ComplianceReport {
+        as_of: chrono::Utc::now(),
+        data_sources: ComplianceDataSources {
+            airtable: raw_body["records"].take(),
+            cache: ComplianceCacheEntries {
+                kyc_cache: state.kyc_cache.entries(&wallet_address),
+                stale_kyc_cache: state.stale_kyc_cache.entries(&wallet_address),
+            },
+            audit_log: state.timeline.get(&account_id),
+            kyc_bypass_status: state.config.kyc_bypass_accounts.get(&account_id).copied(),
+        },
+        account_id,
+    }))
+}
+
+const MAX_REFRESHES_PER_MINUTE: usize = 5;
+
+/// Evicts the cached KYC statuses of the account and looks it up in Airtable again, so support
+/// staff can make a status change visible right away. The last known status is dropped as well
+/// when the lookup fails, so it is not served during an Airtable outage either.
+async fn refresh_account_kyc_status(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<KycResponse>, KycError> {
+    ensure_account_network(&state, &account_id)?;
+    if !state.refresh_rate_limiter.try_acquire(account_id.clone()) {
+        return Err(KycError::TooManyRequests);
+    }
+
+    let view = &state.config.airtable_view_name;
+    let wallet_address = wallet::WalletAddress::from(account_id.clone());
+    let old_kyc_status = state.kyc_cache.get(&wallet_address, view);
+    state.kyc_cache.evict(&wallet_address);
+    state
+        .timeline
+        .record(&account_id, timeline::TimelineEvent::CacheEvicted);
+    // A successful lookup replaces the last known status, which is kept until then so a change is
+    // still recorded in the timeline and sent to the webhooks
+    let kyc_status = match lookup_kyc_status(&state, &account_id, view).await {
+        Ok(kyc_status) => kyc_status,
+        Err(err) => {
+            state.stale_kyc_cache.evict(&wallet_address);
+            return Err(err);
+        }
+    };
+    tracing::info!(
+        %account_id,
+        requester_ip = audit::requester_network(&headers, &state.config.trusted_proxy_cidrs)
+            .as_deref()
+            .unwrap_or("unknown"),
+        ?old_kyc_status,
+        new_kyc_status = ?kyc_status,
+        "KYC status was refreshed"
+    );
+
+    Ok(Json(KycResponse {
+        account_id,
+        kyc_status,
+        network: state.config.near_network,
+    }))
+}
+
+/// Lists what the proxy did about the account's KYC, oldest first.
+async fn get_account_kyc_timeline(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+) -> Json<Vec<timeline::TimelineEntry>> {
+    Json(state.timeline.get(&account_id))
+}
+
+const DEFAULT_AUDIT_PAGE_SIZE: usize = 100;
+const MAX_AUDIT_PAGE_SIZE: usize = 1000;
+
+#[derive(serde::Deserialize)]
+struct AuditQuery {
+    /// `next_cursor` of the previous page.
+    cursor: Option<u64>,
+    limit: Option<usize>,
+}
+
+#[derive(serde::Serialize)]
+struct AuditResponse {
+    account_id: near_account_id::AccountId,
+    events: Vec<timeline::TimelineEntry>,
+    next_cursor: Option<u64>,
+}
+
+/// Pages through the audit trail of the account, oldest first, e.g. for a regulatory inquiry.
+async fn get_account_audit_events(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+) -> Json<AuditResponse> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_PAGE_SIZE)
+        .clamp(1, MAX_AUDIT_PAGE_SIZE);
+    let events = state.timeline.page(&account_id, query.cursor, limit);
+    let next_cursor = (events.len() == limit)
+        .then(|| events.last().map(|entry| entry.id))
+        .flatten();
+    Json(AuditResponse {
+        account_id,
+        events,
+        next_cursor,
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct ErasureQuery {
+    /// Who handles the erasure request, recorded in the deletion log. Part of the signed path, so
+    /// it cannot be changed without the admin secret.
+    operator: Option<String>,
+}
+
+/// Erases the data the proxy keeps about the account upon a GDPR erasure request (the cached
+/// statuses, the timeline its audit events are served from and the ownership nonces), and also
+/// its Airtable records if `GDPR_DELETE_FROM_AIRTABLE` is enabled. The erasure is recorded in the
+/// deletion log.
+async fn erase_account_kyc_data(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<ErasureQuery>,
+    headers: axum::http::HeaderMap,
+) -> Result<axum::http::StatusCode, KycError> {
+    let wallet_address = wallet::WalletAddress::from(account_id.clone());
+    state.kyc_cache.evict(&wallet_address);
+    state.stale_kyc_cache.evict(&wallet_address);
+    state.timeline.evict(&account_id);
+    state.ownership_nonces.evict(&account_id);
+
+    let mut deleted_airtable_records = 0;
+    if state.config.gdpr_delete_from_airtable {
+        let formula = account_id::airtable_filter_formula(
+            &account_id,
+            state.config.airtable_filter_template.as_deref(),
+        );
+        // The wallets of the other chains are erased along with the account they belong to
+        for record in fetch_all_airtable_records(&state, &formula, None).await? {
+            send_airtable_request(
+                &state,
+                state.airtable_client.request(
+                    reqwest::Method::DELETE,
+                    &format!("{AIRTABLE_TABLE}/{}", record.id),
+                ),
+            )
+            .await?
+            .error_for_status()
+            .map_err(|_| KycError::DatabaseError)?;
+            deleted_airtable_records += 1;
+        }
+    }
+
+    let entry = audit::DeletionLogEntry {
+        account_id,
+        operator: query.operator.unwrap_or_else(|| "admin".to_string()),
+        requester_ip: audit::requester_network(&headers, &state.config.trusted_proxy_cidrs),
+        deleted_airtable_records,
+        timestamp: chrono::Utc::now(),
+    };
+    tracing::info!(
+        target: audit::AUDIT_TARGET,
+        account_id = %entry.account_id,
+        requester_ip = entry.requester_ip.as_deref().unwrap_or("unknown"),
+        operator = entry.operator,
+        deleted_airtable_records,
+        timestamp = %entry.timestamp.to_rfc3339(),
+        "KYC data was erased"
+    );
+    state.deletion_log.record(entry);
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+/// Lists the erasures handled since the service started, oldest first.
+async fn get_deletion_log(
+    State(state): State<std::sync::Arc<AppState>>,
+) -> Json<Vec<audit::DeletionLogEntry>> {
+    Json(state.deletion_log.entries())
+}
+
+fn ensure_account_network(
+    state: &AppState,
+    account_id: &near_account_id::AccountId,
+) -> Result<(), KycError> {
+    if account_id::belongs_to_network(account_id, state.config.near_network) {
+        Ok(())
+    } else {
+        Err(KycError::InvalidInput(format!(
+            "{account_id} is not a {} account",
+            state.config.near_network
+        )))
+    }
+}
+
+/// Rejects the request unless the header carries the configured token. Endpoints without a
+/// configured token are disabled.
+fn check_token(
+    headers: &axum::http::HeaderMap,
+    header_name: &str,
+    expected: &Option<String>,
+) -> Result<(), KycError> {
+    let provided = headers
+        .get(header_name)
+        .and_then(|value| value.to_str().ok());
+    match (expected, provided) {
+        (Some(expected), Some(provided)) if expected == provided => Ok(()),
+        _ => Err(KycError::Unauthorized),
+    }
+}
+
+#[derive(serde::Serialize)]
+struct RegistrationLinkResponse {
+    link: String,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Generates a KYC portal link for the account, signed so the portal can trust the account it is
+/// pre-filled with.
+async fn get_registration_link(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+) -> Result<Json<RegistrationLinkResponse>, axum::response::Response> {
+    let Some(link_config) = &state.config.registration_link else {
+        return Err(axum::http::StatusCode::NOT_FOUND.into_response());
+    };
+    ensure_account_network(&state, &account_id).map_err(IntoResponse::into_response)?;
+
+    let expires_at =
+        chrono::Utc::now() + chrono::TimeDelta::seconds(state.config.registration_link_ttl_secs);
+    let link =
+        registration_link::registration_link(link_config, &account_id, expires_at.timestamp());
+    state
+        .timeline
+        .record(&account_id, timeline::TimelineEvent::PortalLinkGenerated);
+    Ok(Json(RegistrationLinkResponse {
+        link: link.into(),
+        expires_at,
+    }))
+}
+
+#[derive(serde::Serialize)]
+struct NonceResponse {
+    nonce: String,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Issues a nonce the account owner signs to prove the ownership with
+/// [`verify_account_ownership`].
+async fn get_ownership_nonce(
+    account_id::AccountIdPath(account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<NonceResponse>, KycError> {
+    let requester_ip = audit::requester_ip(&headers, &state.config.trusted_proxy_cidrs)
+        .unwrap_or_else(|| "unknown".to_string());
+    if !state.nonce_rate_limiter.try_acquire(requester_ip) {
+        return Err(KycError::TooManyRequests);
+    }
+    Ok(Json(NonceResponse {
+        nonce: state.ownership_nonces.issue(account_id),
+        expires_at: chrono::Utc::now()
+            + chrono::TimeDelta::from_std(ownership::NONCE_TTL).expect("the TTL is small"),
+    }))
+}
+
+#[derive(serde::Serialize)]
+struct OwnershipResponse {
+    account_id: near_account_id::AccountId,
+    ownership_verified: bool,
+}
+
+/// Checks that the nonce was signed with an access key of the account. Every nonce can only be
+/// used once, whether the verification succeeds or not.
+async fn verify_account_ownership(
+    State(state): State<std::sync::Arc<AppState>>,
+    Json(proof): Json<ownership::OwnershipProof>,
+) -> Result<Json<OwnershipResponse>, KycError> {
+    let ownership_verified = state
+        .ownership_nonces
+        .consume(&proof.account_id, &proof.nonce)
+        && ownership::verify_signature(&proof)
+        && ownership::is_access_key(&state, &proof.account_id, &proof.public_key).await?;
+    Ok(Json(OwnershipResponse {
+        account_id: proof.account_id,
+        ownership_verified,
+    }))
+}
+
+const MAX_OWNERSHIP_BATCHES_PER_MINUTE: usize = 10;
+const MAX_NONCES_PER_MINUTE: usize = 30;
+const MAX_OWNERSHIP_BATCH_SIZE: usize = 200;
+
+#[derive(serde::Serialize)]
+struct BatchOwnershipItem {
+    account_id: near_account_id::AccountId,
+    ownership_verified: bool,
+    /// Why the ownership could not be checked, e.g. the NEAR RPC failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Same as [`verify_account_ownership`] for many accounts at once, e.g. all the applicants of a
+/// grant round. The signatures are verified concurrently on the blocking thread pool as the
+/// verification is CPU-bound.
+async fn verify_batch_account_ownership(
+    State(state): State<std::sync::Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(proofs): Json<Vec<ownership::OwnershipProof>>,
+) -> Result<Json<Vec<BatchOwnershipItem>>, KycError> {
+    if proofs.len() > MAX_OWNERSHIP_BATCH_SIZE {
+        return Err(KycError::InvalidInput(format!(
+            "At most {MAX_OWNERSHIP_BATCH_SIZE} ownership proofs can be verified at once"
+        )));
+    }
+    let requester_ip = audit::requester_ip(&headers, &state.config.trusted_proxy_cidrs)
+        .unwrap_or_else(|| "unknown".to_string());
+    if !state.ownership_batch_rate_limiter.try_acquire(requester_ip) {
+        return Err(KycError::TooManyRequests);
+    }
+
+    let mut verifications = tokio::task::JoinSet::new();
+    for (index, proof) in proofs.into_iter().enumerate() {
+        let state = state.clone();
+        verifications.spawn(async move {
+            let nonce_valid = state
+                .ownership_nonces
+                .consume(&proof.account_id, &proof.nonce);
+            let signature_valid = nonce_valid && {
+                let proof = proof.clone();
+                tokio::task::spawn_blocking(move || ownership::verify_signature(&proof))
+                    .await
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+            };
+            let ownership_verified = if signature_valid {
+                ownership::is_access_key(&state, &proof.account_id, &proof.public_key).await
+            } else {
+                Ok(false)
+            };
+            let item = match ownership_verified {
+                Ok(ownership_verified) => BatchOwnershipItem {
+                    account_id: proof.account_id,
+                    ownership_verified,
+                    error: None,
+                },
+                Err(err) => BatchOwnershipItem {
+                    account_id: proof.account_id,
+                    ownership_verified: false,
+                    error: Some(err.status_and_message().1),
+                },
+            };
+            (index, item)
+        });
+    }
+
+    let mut items = Vec::with_capacity(verifications.len());
+    while let Some(verification) = verifications.join_next().await {
+        items.push(verification.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic())));
+    }
+    items.sort_unstable_by_key(|(index, _)| *index);
+    Ok(Json(items.into_iter().map(|(_, item)| item).collect()))
+}
+
+const MAX_SEEN_WEBHOOK_EVENTS: usize = 10_000;
+
+#[derive(serde::Deserialize)]
+struct AirtableWebhookEvent {
+    event_id: String,
+    account_id: near_account_id::AccountId,
+}
+
+/// Evicts the cached KYC statuses of an account whose Airtable record has changed. Redelivered
+/// events are acknowledged without being processed again.
+async fn handle_airtable_webhook(
+    State(state): State<std::sync::Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(event): Json<AirtableWebhookEvent>,
+) -> Result<axum::http::StatusCode, KycError> {
+    check_token(
+        &headers,
+        "X-Webhook-Token",
+        &state.config.airtable_webhook_token,
+    )?;
+
+    if !state.seen_webhook_events.insert(&event.event_id) {
+        tracing::debug!(event_id = %event.event_id, "Duplicate Airtable webhook event was ignored");
+        return Ok(axum::http::StatusCode::OK);
+    }
+    state.kyc_cache.evict(&event.account_id.clone().into());
+    state
+        .timeline
+        .record(&event.account_id, timeline::TimelineEvent::CacheEvicted);
+    tracing::info!(
+        event_id = %event.event_id,
+        account_id = %event.account_id,
+        "Cached KYC status was evicted by an Airtable webhook"
+    );
+
+    Ok(axum::http::StatusCode::OK)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, serde::Deserialize)]
+enum Chain {
+    #[serde(rename = "NEAR")]
+    Near,
+    #[serde(rename = "ETH")]
+    Eth,
+    #[serde(rename = "SOL")]
+    Sol,
+}
+
+impl Chain {
+    /// Value of the Airtable `chain` field.
+    fn airtable_value(self) -> &'static str {
+        match self {
+            Chain::Near => "NEAR",
+            Chain::Eth => "ETH",
+            Chain::Sol => "SOL",
+        }
+    }
+
+    /// Only NEAR accounts are registered and listed, the wallets of other chains are linked to one.
+    fn ensure_near(chain: Option<Chain>) -> Result<(), KycError> {
+        match chain {
+            None | Some(Chain::Near) => Ok(()),
+            Some(chain) => Err(KycError::InvalidInput(format!(
+                "Only NEAR accounts are supported, not {}",
+                chain.airtable_value()
+            ))),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct RegistrationRequest {
+    account_id: near_account_id::AccountId,
+    chain: Chain,
+}
+
+/// Creates a not submitted KYC record in Airtable, so the account shows up in the KYC pipeline
+/// without being added to the table manually.
+async fn register_account(
+    State(state): State<std::sync::Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(request): Json<RegistrationRequest>,
+) -> Result<(axum::http::StatusCode, Json<KycResponse>), KycError> {
+    check_token(
+        &headers,
+        "X-Registration-Token",
+        &state.config.registration_token,
+    )?;
+
+    let RegistrationRequest { account_id, chain } = request;
+    Chain::ensure_near(Some(chain))?;
+    ensure_account_network(&state, &account_id)?;
+
+    // Any record of the account counts, including the ones the configured view filters out
+    let existing_records: AirtableResponse = query_airtable(
+        &state,
+        &[
+            ("maxRecords", "1"),
+            (
+                "filterByFormula",
+                &account_id::airtable_filter_formula(
+                    &account_id,
+                    state.config.airtable_filter_template.as_deref(),
+                ),
+            ),
+        ],
+    )
+    .await?;
+    if !existing_records.records.is_empty() {
+        return Err(KycError::AccountAlreadyRegistered);
+    }
+
+    send_airtable_request(
+        &state,
+        state
+            .airtable_client
+            .request(reqwest::Method::POST, AIRTABLE_TABLE)
+            .json(&serde_json::json!({
+                "fields": {
+                    "near_wallet": account_id,
+                    "Owner Verification Status": "Not Submitted",
+                }
+            })),
+    )
+    .await?
+    .error_for_status()
+    .map_err(|_| KycError::DatabaseError)?;
+
+    Ok((
+        axum::http::StatusCode::CREATED,
+        Json(KycResponse {
+            account_id,
+            kyc_status: KycStatus::NotSubmitted,
+            network: state.config.near_network,
+        }),
+    ))
+}
+
+#[derive(serde::Deserialize)]
+struct ExpiredKycQuery {
+    /// Only the NEAR accounts are listed, so the filter only validates the value.
+    chain: Option<Chain>,
+    offset: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ExpiredKycRecord {
+    account_id: String,
+    verification_date: Option<chrono::DateTime<chrono::Utc>>,
+    expiry_date: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(serde::Serialize)]
+struct ExpiredKycResponse {
+    records: Vec<ExpiredKycRecord>,
+    /// Pass it back as the `offset` query parameter to fetch the next page.
+    offset: Option<String>,
+}
+
+/// Lists accounts which have to re-verify, so operators can reach out to them. Records without an
+/// approval standing are expired too, so the records are filtered by their parsed KYC status
+/// rather than by the `approval_standing` value alone. Pages may therefore hold fewer records than
+/// Airtable returned.
+async fn get_expired_kyc_accounts(
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(ExpiredKycQuery { chain, offset }): Query<ExpiredKycQuery>,
+) -> Result<Json<ExpiredKycResponse>, KycError> {
+    Chain::ensure_near(chain)?;
+    let mut query = vec![("view", state.config.airtable_view_name.as_str())];
+    // The standing of the linked contact takes precedence, so active records may be expired too
+    if !state.config.airtable_follow_linked_records {
+        query.push(("filterByFormula", "NOT({approval_standing}='active')"));
+    }
+    if let Some(offset) = &offset {
+        query.push(("offset", offset.as_str()));
+    }
+    let mut page: AirtableResponse = query_airtable(&state, &query).await?;
+    if state.config.airtable_follow_linked_records {
+        apply_contact_approval_standing(&state, &mut page.records).await;
+    }
+
+    Ok(Json(ExpiredKycResponse {
+        records: page
+            .records
+            .into_iter()
+            .filter(|record| {
+                record.is_near_record() && record_kyc_status(record) == KycStatus::Expired
+            })
+            .filter_map(|record| {
+                let verification_date = record.fields.approval_date;
+                Some(ExpiredKycRecord {
+                    account_id: record.fields.near_wallet?,
+                    verification_date,
+                    expiry_date: verification_date.map(|verification_date| {
+                        verification_date + chrono::Duration::days(state.config.kyc_expiry_days)
+                    }),
+                })
+            })
+            .collect(),
+        offset: page.offset,
+    }))
+}
+
+const HEALTH_STATS_WINDOW: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+#[derive(serde::Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    airtable_success_rate_5min: Option<f64>,
+    airtable_p95_ms_5min: Option<u128>,
+    airtable_p99_ms_5min: Option<u128>,
+    airtable_error_count_5min: usize,
+    quota_warning: bool,
+    /// The provider health check probe failed several times in a row.
+    provider_degraded: bool,
+}
+
+async fn get_health(State(state): State<std::sync::Arc<AppState>>) -> Json<HealthResponse> {
+    let airtable_stats = state.airtable_stats.summary(HEALTH_STATS_WINDOW);
+    Json(HealthResponse {
+        status: "ok",
+        airtable_success_rate_5min: airtable_stats.success_rate,
+        airtable_p95_ms_5min: airtable_stats.p95_ms,
+        airtable_p99_ms_5min: airtable_stats.p99_ms,
+        airtable_error_count_5min: airtable_stats.error_count,
+        quota_warning: state.airtable_quota.is_warning(),
+        provider_degraded: state.provider_health.is_degraded(),
+    })
+}
+
+const STATS_CACHE_HIT_RATE_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Operational stats of the service, for dashboards which do not scrape `/metrics`.
+#[derive(serde::Serialize)]
+struct Stats {
+    cache_size: usize,
+    cache_hit_rate_1min: Option<f64>,
+    stale_cache_size: usize,
+    circuit_breaker_state: circuit_breaker::CircuitState,
+    airtable_calls_total: u64,
+    airtable_errors_total: u64,
+    /// Airtable requests currently holding a request permit.
+    in_flight_requests: usize,
+    uptime_secs: u64,
+}
+
+fn collect_stats(state: &AppState) -> Stats {
+    let (airtable_calls_total, airtable_errors_total) = state.metrics.airtable_call_totals();
+    Stats {
+        cache_size: state.kyc_cache.len(),
+        cache_hit_rate_1min: state.kyc_cache.hit_rate(STATS_CACHE_HIT_RATE_WINDOW),
+        stale_cache_size: state.stale_kyc_cache.len(),
+        circuit_breaker_state: state.airtable_circuit_breaker().state(),
+        airtable_calls_total,
+        airtable_errors_total,
+        in_flight_requests: state
+            .config
+            .airtable_max_concurrent_requests
+            .saturating_sub(state.airtable_request_permits.available_permits()),
+        uptime_secs: state.started_at.elapsed().as_secs(),
+    }
+}
+
+async fn get_stats(State(state): State<std::sync::Arc<AppState>>) -> Json<Stats> {
+    Json(collect_stats(&state))
+}
+
+async fn get_metrics(State(state): State<std::sync::Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4",
+        )],
+        state.metrics.render(),
+    )
+}
+
+#[derive(serde::Serialize)]
+struct ProbeResponse {
+    circuit_breaker_state: circuit_breaker::CircuitState,
+}
+
+/// Kubernetes liveness probe: fails while the Airtable circuit breaker of the individual lookups
+/// is open, so a pod stuck returning 503 to every client gets restarted. Recommended probe
+/// configuration:
+///
+/// ```yaml
+/// livenessProbe:
+///   httpGet:
+///     path: /livez
+///     port: 8000
+///   periodSeconds: 10
+///   failureThreshold: 6
+/// readinessProbe:
+///   httpGet:
+///     path: /readyz
+///     port: 8000
+///   periodSeconds: 5
+///   failureThreshold: 2
+/// ```
+///
+/// The liveness `failureThreshold` should cover a few circuit breaker open periods, so that a
+/// short Airtable outage does not restart the pod while the half-open probes can still recover.
+/// The probe passes while the circuit breaker is held open by a used up Airtable quota, as a
+/// restart would reset the quota counter.
+async fn get_liveness(
+    State(state): State<std::sync::Arc<AppState>>,
+) -> (axum::http::StatusCode, Json<ProbeResponse>) {
+    let (status, response) = probe_response(&state);
+    if state.airtable_circuit_breaker().is_held_open() {
+        (axum::http::StatusCode::OK, response)
+    } else {
+        (status, response)
+    }
+}
+
+/// Kubernetes readiness probe. The service has no dependencies besides Airtable yet, so it
+/// reports the same circuit breaker state as [`get_liveness`].
+async fn get_readiness(
+    State(state): State<std::sync::Arc<AppState>>,
+) -> (axum::http::StatusCode, Json<ProbeResponse>) {
+    probe_response(&state)
+}
+
+fn probe_response(state: &AppState) -> (axum::http::StatusCode, Json<ProbeResponse>) {
+    let circuit_breaker_state = state.airtable_circuit_breaker().state();
+    let status = if circuit_breaker_state == circuit_breaker::CircuitState::Open {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        axum::http::StatusCode::OK
+    };
+    (
+        status,
+        Json(ProbeResponse {
+            circuit_breaker_state,
+        }),
+    )
+}
+
+const MAX_BATCH_SIZE: usize = 1000;
+
+#[derive(serde::Deserialize)]
+struct BatchRequest {
+    /// Wallet addresses; only NEAR accounts can be looked up.
+    #[serde(deserialize_with = "wallet::deserialize_wallet_addresses")]
+    account_ids: Result<Vec<wallet::WalletAddress>, Vec<String>>,
+}
+
+#[derive(serde::Deserialize)]
+struct BatchQuery {
+    /// Stream the results as NDJSON in completion order instead of a JSON array in request order.
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum BatchItem {
+    Found(KycResponse),
+    Failed {
+        account_id: wallet::WalletAddress,
+        error: String,
+    },
+}
+
+async fn lookup_batch_item(state: &AppState, wallet_address: wallet::WalletAddress) -> BatchItem {
+    let wallet::WalletAddress::Near(account_id) = wallet_address else {
+        return BatchItem::Failed {
+            account_id: wallet_address,
+            error: "Only NEAR accounts can be looked up".to_string(),
+        };
+    };
+    let kyc_status = match ensure_account_network(state, &account_id) {
+        Ok(()) => {
+            circuit_breaker::in_batch_circuit(lookup_kyc_status(
+                state,
+                &account_id,
+                &state.config.airtable_view_name,
+            ))
+            .await
+        }
+        Err(err) => Err(err),
+    };
+    match kyc_status {
+        Ok(kyc_status) => BatchItem::Found(KycResponse {
+            account_id,
+            kyc_status,
+            network: state.config.near_network,
+        }),
+        Err(err) => BatchItem::Failed {
+            account_id: account_id.into(),
+            error: err.status_and_message().1,
+        },
+    }
+}
+
+/// Spawns a lookup task per wallet address, tagged with the address index. The tasks are aborted
+/// when the set is dropped, e.g. if the client goes away.
+fn spawn_batch_lookups(
+    state: &std::sync::Arc<AppState>,
+    wallet_addresses: Vec<wallet::WalletAddress>,
+) -> tokio::task::JoinSet<(usize, BatchItem)> {
+    let mut lookups = tokio::task::JoinSet::new();
+    for (index, wallet_address) in wallet_addresses.into_iter().enumerate() {
+        let state = state.clone();
+        lookups.spawn(async move { (index, lookup_batch_item(&state, wallet_address).await) });
+    }
+    lookups
+}
+
+/// Looks up the wallet addresses concurrently, returning the results in the same order.
+async fn lookup_batch_items(
+    state: &std::sync::Arc<AppState>,
+    wallet_addresses: Vec<wallet::WalletAddress>,
+) -> Vec<BatchItem> {
+    let item_count = wallet_addresses.len();
+    let mut lookups = spawn_batch_lookups(state, wallet_addresses);
+    let mut items = Vec::with_capacity(item_count);
+    while let Some(lookup) = lookups.join_next().await {
+        items.push(lookup.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic())));
+    }
+    items.sort_unstable_by_key(|(index, _)| *index);
+    items.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Looks up KYC statuses of multiple accounts at once.
+async fn get_batch_kyc_status(
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<BatchQuery>,
+    Json(request): Json<BatchRequest>,
+) -> Result<axum::response::Response, KycError> {
+    let account_ids = request.account_ids.map_err(KycError::InvalidAccountIds)?;
+    if account_ids.len() > MAX_BATCH_SIZE {
+        return Err(KycError::InvalidInput(format!(
+            "At most {MAX_BATCH_SIZE} accounts can be looked up at once"
+        )));
+    }
+
+    if !query.stream {
+        let items = lookup_batch_items(&state, account_ids).await;
+        return Ok(Json(items).into_response());
+    }
+
+    let lookups = spawn_batch_lookups(&state, account_ids);
+
+    // Every result is sent as soon as its lookup completes, so clients can start processing
+    // before the slowest lookup is done
+    let lines = futures_util::stream::unfold(lookups, |mut lookups| async move {
+        let (_, item) = lookups
+            .join_next()
+            .await?
+            .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+        let mut line = serde_json::to_vec(&item).expect("batch items are serializable");
+        line.push(b'\n');
+        Some((Ok::<_, std::convert::Infallible>(line), lookups))
+    });
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "application/x-ndjson")],
+        axum::body::Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+#[derive(serde::Deserialize)]
+struct BatchDiffRequest {
+    /// Previously looked up statuses to compare the current ones with.
+    snapshot_a: Vec<KycSnapshotItem>,
+    #[serde(deserialize_with = "wallet::deserialize_wallet_addresses")]
+    account_ids: Result<Vec<wallet::WalletAddress>, Vec<String>>,
+}
+
+#[derive(serde::Deserialize)]
+struct KycSnapshotItem {
+    account_id: wallet::WalletAddress,
+    kyc_status: KycStatus,
+}
+
+#[derive(serde::Serialize)]
+struct KycStatusChange {
+    account_id: near_account_id::AccountId,
+    from: KycStatus,
+    to: KycStatus,
+}
+
+#[derive(Default, serde::Serialize)]
+struct BatchDiffResponse {
+    changed: Vec<KycStatusChange>,
+    unchanged: Vec<KycResponse>,
+    /// Accounts which are not in the snapshot.
+    new_accounts: Vec<KycResponse>,
+    failed: Vec<BatchItem>,
+}
+
+/// Looks up the current KYC statuses of the accounts and compares them with a snapshot, e.g. to
+/// find the accounts approved since the last grant payout.
+async fn get_batch_kyc_status_diff(
+    State(state): State<std::sync::Arc<AppState>>,
+    Json(request): Json<BatchDiffRequest>,
+) -> Result<Json<BatchDiffResponse>, KycError> {
+    let account_ids = request.account_ids.map_err(KycError::InvalidAccountIds)?;
+    if account_ids.len() > MAX_BATCH_SIZE {
+        return Err(KycError::InvalidInput(format!(
+            "At most {MAX_BATCH_SIZE} accounts can be looked up at once"
+        )));
+    }
+
+    let snapshot = request
+        .snapshot_a
+        .into_iter()
+        .map(|item| (item.account_id, item.kyc_status))
+        .collect::<std::collections::HashMap<_, _>>();
+    let mut diff = BatchDiffResponse::default();
+    for item in lookup_batch_items(&state, account_ids).await {
+        let BatchItem::Found(response) = item else {
+            diff.failed.push(item);
+            continue;
+        };
+        match snapshot.get(&response.account_id.clone().into()) {
+            None => diff.new_accounts.push(response),
+            Some(&kyc_status) if kyc_status == response.kyc_status => diff.unchanged.push(response),
+            Some(&kyc_status) => diff.changed.push(KycStatusChange {
+                account_id: response.account_id,
+                from: kyc_status,
+                to: response.kyc_status,
+            }),
+        }
+    }
+    Ok(Json(diff))
+}
+
+const MAX_ASYNC_BATCH_SIZE: usize = 10_000;
+
+/// Queues a batch lookup which is too large for a synchronous response. The results are polled
+/// with [`get_batch_job`].
+async fn create_batch_job(
+    State(state): State<std::sync::Arc<AppState>>,
+    Json(request): Json<BatchRequest>,
+) -> Result<(axum::http::StatusCode, Json<serde_json::Value>), KycError> {
+    let account_ids = request.account_ids.map_err(KycError::InvalidAccountIds)?;
+    if account_ids.len() > MAX_ASYNC_BATCH_SIZE {
+        return Err(KycError::InvalidInput(format!(
+            "At most {MAX_ASYNC_BATCH_SIZE} accounts can be looked up in a batch job"
+        )));
+    }
+
+    let job_id = state.batch_jobs.create(account_ids.len());
+    let worker = tokio::spawn({
+        let state = state.clone();
+        async move {
+            state.batch_jobs.start(job_id);
+            let results =
+                futures_util::future::join_all(account_ids.into_iter().map(|account_id| {
+                    let state = &state;
+                    async move {
+                        let item = lookup_batch_item(state, account_id).await;
+                        state.batch_jobs.record_progress(job_id);
+                        item
+                    }
+                }))
+                .await;
+            state.batch_jobs.complete(job_id, results);
+        }
+    });
+    tokio::spawn(async move {
+        if let Err(err) = worker.await {
+            tracing::error!(%job_id, %err, "Batch job worker failed");
+            state.batch_jobs.fail(job_id);
+        }
+    });
+
+    Ok((
+        axum::http::StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "job_id": job_id, "status": "queued" })),
+    ))
+}
+
+async fn get_batch_job(
+    State(state): State<std::sync::Arc<AppState>>,
+    axum::extract::Path(job_id): axum::extract::Path<uuid::Uuid>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    state
+        .batch_jobs
+        .to_json(job_id)
+        .map(Json)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+/// Groups the records by their account, skipping the records without a valid account ID.
+fn group_records_by_account(
+    records: Vec<AirtableRecord>,
+) -> std::collections::BTreeMap<near_account_id::AccountId, Vec<AirtableRecord>> {
+    let mut records_by_account = std::collections::BTreeMap::<_, Vec<_>>::new();
+    for record in records {
+        if let Some(account_id) = record_account_id(&record) {
+            records_by_account
+                .entry(account_id)
+                .or_default()
+                .push(record);
+        }
+    }
+    records_by_account
+}
+
+/// The NEAR account the record holds the status of, if any. The records of the wallets on other
+/// chains hold the status of the wallet rather than of the account in their `near_wallet`.
+fn record_account_id(record: &AirtableRecord) -> Option<near_account_id::AccountId> {
+    if !record.is_near_record() {
+        return None;
+    }
+    record.fields.near_wallet.as_deref()?.parse().ok()
+}
+
+const MAX_SUB_ACCOUNT_RECORDS: u32 = 1000;
+const MAX_SUB_ACCOUNT_MEMBERS: usize = 200;
+
+/// Lists KYC statuses of all the sub-accounts of the parent account (e.g. members of a DAO).
+async fn get_sub_account_members(
+    account_id::AccountIdPath(parent_account_id): account_id::AccountIdPath,
+    State(state): State<std::sync::Arc<AppState>>,
+) -> Result<Json<Vec<KycResponse>>, KycError> {
+    // Account IDs can only contain `.` as a regex special character, and a character class avoids
+    // dealing with backslash escaping in Airtable string literals
+    let formula = format!(
+        "REGEX_MATCH({{near_wallet}}, '[.]{}$')",
+        parent_account_id.as_str().replace('.', "[.]")
+    );
+    let records =
+        fetch_all_airtable_records(&state, &formula, Some(MAX_SUB_ACCOUNT_RECORDS)).await?;
+
+    Ok(Json(
+        group_records_by_account(records)
+            .into_iter()
+            .take(MAX_SUB_ACCOUNT_MEMBERS)
+            .map(|(account_id, records)| KycResponse {
+                account_id,
+                kyc_status: select_kyc_status(&records, state.config.tie_breaking_policy).0,
+                network: state.config.near_network,
+            })
+            .collect(),
+    ))
+}
+
+const MAX_SAMPLE_RECORDS: u32 = 1000;
+const DEFAULT_SAMPLE_SIZE: usize = 10;
+const MAX_SAMPLE_SIZE: usize = 50;
+
+#[derive(serde::Deserialize)]
+struct RandomSampleQuery {
+    n: Option<usize>,
+    status: Option<KycStatus>,
+    /// Only the NEAR accounts are sampled, so the filter only validates the value.
+    chain: Option<Chain>,
+}
+
+/// Picks random accounts of the KYC table, optionally with the given status, so their statuses
+/// can be checked after a deployment.
+async fn get_random_kyc_sample(
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(RandomSampleQuery { n, status, chain }): Query<RandomSampleQuery>,
+) -> Result<Json<Vec<KycResponse>>, KycError> {
+    Chain::ensure_near(chain)?;
+    use ring::rand::SecureRandom;
+
+    let records =
+        fetch_all_airtable_records(&state, "NOT({near_wallet}='')", Some(MAX_SAMPLE_RECORDS))
+            .await?;
+    let mut accounts = group_records_by_account(records)
+        .into_iter()
+        .map(|(account_id, records)| KycResponse {
+            account_id,
+            kyc_status: select_kyc_status(&records, state.config.tie_breaking_policy).0,
+            network: state.config.near_network,
+        })
+        .filter(|account| status.is_none_or(|status| account.kyc_status == status))
+        .collect::<Vec<_>>();
+
+    // Partial Fisher-Yates shuffle, moving the sample to the front
+    let sample_size = n
+        .unwrap_or(DEFAULT_SAMPLE_SIZE)
+        .min(MAX_SAMPLE_SIZE)
+        .min(accounts.len());
+    let random = ring::rand::SystemRandom::new();
+    for index in 0..sample_size {
+        let mut bytes = [0; 8];
+        random
+            .fill(&mut bytes)
+            .expect("the system random number generator is available");
+        let remaining = (accounts.len() - index) as u64;
+        accounts.swap(
+            index,
+            index + (u64::from_le_bytes(bytes) % remaining) as usize,
+        );
+    }
+    accounts.truncate(sample_size);
+    Ok(Json(accounts))
+}
+
+const MAX_SUMMARY_RECORDS: u32 = 5000;
+const MAX_SUMMARY_ACCOUNTS: usize = 500;
+
+#[derive(serde::Deserialize)]
+struct KycSummaryQuery {
+    status: Option<KycStatus>,
+}
+
+#[derive(serde::Serialize)]
+struct KycSummaryItem {
+    account_id: near_account_id::AccountId,
+    kyc_status: KycStatus,
+}
+
+/// Lists the KYC statuses of all the accounts in the KYC table, without any other fields, to
+/// help debugging why an account is blocked.
+async fn get_kyc_summary(
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<KycSummaryQuery>,
+) -> Result<Json<Vec<KycSummaryItem>>, KycError> {
+    let mut records = std::pin::pin!(stream_airtable_records(
+        &state,
+        "NOT({near_wallet}='')",
+        Some(MAX_SUMMARY_RECORDS)
+    ));
+    // Only the record [`select_kyc_status`] would pick is kept for every account, instead of all
+    // the records of the table
+    let mut record_by_account = std::collections::BTreeMap::new();
+    while let Some(record) = futures_util::TryStreamExt::try_next(&mut records).await? {
+        let Some(account_id) = record_account_id(&record) else {
+            continue;
+        };
+        match record_by_account.entry(account_id) {
+            std::collections::btree_map::Entry::Vacant(entry) => {
+                entry.insert(record);
+            }
+            std::collections::btree_map::Entry::Occupied(mut entry) => {
+                if state
+                    .config
+                    .tie_breaking_policy
+                    .prefers(&record, entry.get())
+                {
+                    entry.insert(record);
+                }
+            }
+        }
+    }
+
+    Ok(Json(
+        record_by_account
+            .into_iter()
+            .map(|(account_id, record)| KycSummaryItem {
+                account_id,
+                kyc_status: record_kyc_status(&record),
+            })
+            .filter(|item| query.status.is_none_or(|status| item.kyc_status == status))
+            .take(MAX_SUMMARY_ACCOUNTS)
+            .collect(),
+    ))
+}
+
+const MAX_COHORT_RECORDS: u32 = 1000;
+
+#[derive(serde::Deserialize)]
+struct LeaderboardQuery {
+    cohort: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct Leaderboard {
+    cohort: String,
+    total: usize,
+    approved: usize,
+    pending: usize,
+    rejected: usize,
+    expired: usize,
+    not_submitted: usize,
+    /// Share of the cohort accounts which are approved.
+    completion_rate: f64,
+}
+
+impl Leaderboard {
+    fn new(cohort: String, kyc_statuses: &[KycStatus]) -> Self {
+        let count = |kyc_status| {
+            kyc_statuses
+                .iter()
+                .filter(|&&other| other == kyc_status)
+                .count()
+        };
+        let total = kyc_statuses.len();
+        let approved = count(KycStatus::Approved);
+        Self {
+            cohort,
+            total,
+            approved,
+            pending: count(KycStatus::Pending),
+            rejected: count(KycStatus::Rejected),
+            expired: count(KycStatus::Expired),
+            not_submitted: count(KycStatus::NotSubmitted),
+            completion_rate: if total == 0 {
+                0.0
+            } else {
+                approved as f64 / total as f64
+            },
+        }
+    }
+}
+
+/// Reports how many accounts of a grant cohort completed KYC. The cohort accounts are the records
+/// of the Airtable view named after the cohort, and their statuses are looked up in the default
+/// view.
+async fn get_kyc_leaderboard(
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Json<Leaderboard>, KycError> {
+    if query.cohort.is_empty() {
+        return Err(KycError::InvalidInput("The cohort is required".to_string()));
+    }
+    if let Some(leaderboard) = state.leaderboard_cache.get(&query.cohort) {
+        return Ok(Json(leaderboard));
+    }
+
+    let records = futures_util::TryStreamExt::try_collect::<Vec<_>>(stream_airtable_view_records(
+        &state,
+        &query.cohort,
+        "NOT({near_wallet}='')",
+        Some(MAX_COHORT_RECORDS),
+    ))
+    .await?;
+    let account_ids = group_records_by_account(records).into_keys();
+
+    let mut lookups = tokio::task::JoinSet::new();
+    for account_id in account_ids {
+        let state = state.clone();
+        lookups.spawn(async move {
+            circuit_breaker::in_batch_circuit(lookup_kyc_status(
+                &state,
+                &account_id,
+                &state.config.airtable_view_name,
+            ))
+            .await
+        });
+    }
+    let mut kyc_statuses = Vec::with_capacity(lookups.len());
+    while let Some(lookup) = lookups.join_next().await {
+        kyc_statuses
+            .push(lookup.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))?);
+    }
+
+    let leaderboard = Leaderboard::new(query.cohort, &kyc_statuses);
+    state.leaderboard_cache.insert(leaderboard.clone());
+    Ok(Json(leaderboard))
+}
+
+#[derive(serde::Deserialize)]
+struct AirtableTablesSchema {
+    tables: Vec<AirtableTableSchema>,
+}
+
+#[derive(serde::Deserialize)]
+struct AirtableTableSchema {
+    name: String,
+    fields: Vec<AirtableFieldSchema>,
+}
+
+#[derive(serde::Deserialize)]
+struct AirtableFieldSchema {
+    name: String,
+}
+
+#[derive(serde::Serialize)]
+struct AirtableSchemaReport {
+    expected: Vec<&'static str>,
+    missing: Vec<&'static str>,
+    extra: Vec<String>,
+    compatible: bool,
+}
+
+impl AirtableSchemaReport {
+    /// Compares the columns of the KYC table with the fields the proxy reads.
+    fn new(columns: &[String]) -> Self {
+        let missing = AIRTABLE_FIELD_NAMES
+            .into_iter()
+            .filter(|field| !columns.iter().any(|column| column == field))
+            .collect::<Vec<_>>();
+        let extra = columns
+            .iter()
+            .filter(|column| !AIRTABLE_FIELD_NAMES.contains(&column.as_str()))
+            .cloned()
+            .collect();
+        Self {
+            expected: AIRTABLE_FIELD_NAMES.to_vec(),
+            compatible: missing.is_empty(),
+            missing,
+            extra,
+        }
+    }
+}
+
+/// Column names of the KYC table, from the Airtable Meta API.
+async fn fetch_airtable_columns(state: &AppState) -> Result<Vec<String>, KycError> {
+    let schema: AirtableTablesSchema = send_airtable_request(
+        state,
+        state
+            .airtable_client
+            .tables_schema_request(state.config.airtable_meta_api_key.as_deref()),
+    )
+    .await?
+    .error_for_status()
+    .map_err(|_| KycError::DatabaseError)?
+    .json()
+    .await
+    .map_err(|_| KycError::DeserializationError)?;
+
+    Ok(schema
+        .tables
+        .into_iter()
+        .find(|table| table.name == AIRTABLE_TABLE)
+        .map(|table| table.fields.into_iter().map(|field| field.name).collect())
+        .unwrap_or_default())
+}
+
+/// Compares the columns of the KYC table with the fields the proxy reads, to detect schema
+/// changes before they break the lookups.
+async fn get_airtable_schema(
+    State(state): State<std::sync::Arc<AppState>>,
+) -> Result<Json<AirtableSchemaReport>, KycError> {
+    let columns = fetch_airtable_columns(&state).await?;
+    Ok(Json(AirtableSchemaReport::new(&columns)))
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct CorsConfig {
+    allowed_origins: Vec<String>,
+}
+
+/// Replaces the list of allowed CORS origins without restarting the service.
+async fn update_cors_config(
+    State(state): State<std::sync::Arc<AppState>>,
+    Json(config): Json<CorsConfig>,
+) -> Result<Json<CorsConfig>, KycError> {
+    let allowed_origins = parse_cors_origins(&config.allowed_origins)?;
+    *state.cors_allowed_origins.write().unwrap() = allowed_origins;
+    tracing::info!(allowed_origins = ?config.allowed_origins, "CORS allowed origins were updated");
+
+    Ok(Json(config))
+}
+
+/// Resets the Airtable API call counter, e.g. when the monthly quota renews, and closes the
+/// circuit breaker if the quota was used up.
+async fn reset_airtable_quota(
+    State(state): State<std::sync::Arc<AppState>>,
+) -> axum::http::StatusCode {
+    state.airtable_quota.reset();
+    for circuit_breaker in state.airtable_circuit_breakers.values() {
+        circuit_breaker.release();
+    }
+    tracing::info!("Airtable quota was reset");
+    axum::http::StatusCode::NO_CONTENT
+}
+
+#[derive(serde::Deserialize)]
+struct SimulatedStatusChange {
+    account_id: near_account_id::AccountId,
+    from_status: KycStatus,
+    to_status: KycStatus,
+}
+
+/// Goes through a status change as if a lookup had found it, without touching Airtable, to test
+/// the consumers of the status changes, e.g. the `kyc_status_changed` webhooks. Only routed
+/// outside of production.
+async fn simulate_status_change(
+    State(state): State<std::sync::Arc<AppState>>,
+    Json(change): Json<SimulatedStatusChange>,
+) -> Json<KycResponse> {
+    let view = &state.config.airtable_view_name;
+    state
+        .stale_kyc_cache
+        .insert(&change.account_id.clone().into(), view, change.from_status);
+    store_kyc_status(&state, &change.account_id, view, change.to_status);
+    tracing::info!(
+        account_id = %change.account_id,
+        from_status = ?change.from_status,
+        to_status = ?change.to_status,
+        "Status change was simulated"
+    );
+
+    Json(KycResponse {
+        account_id: change.account_id,
+        kyc_status: change.to_status,
+        network: state.config.near_network,
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct KeyRotationRequest {
+    new_airtable_api_key: String,
+}
+
+/// Switches to a new Airtable API key without a restart. Requests which are already in flight
+/// complete with the old key, which is also tried for `KEY_ROTATION_GRACE_PERIOD_SECS` when
+/// Airtable rejects the new one (e.g. before the new token is granted access to the base).
+async fn rotate_airtable_api_key(
+    State(state): State<std::sync::Arc<AppState>>,
+    Json(request): Json<KeyRotationRequest>,
+) -> Result<axum::http::StatusCode, KycError> {
+    let new_api_key = request.new_airtable_api_key.trim();
+    if new_api_key.is_empty() {
+        return Err(KycError::InvalidInput(
+            "The new Airtable API key is empty".to_string(),
+        ));
+    }
+    state.airtable_client.rotate_api_key(new_api_key);
+    tracing::info!("Airtable API key was rotated");
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+fn parse_cors_origins(origins: &[String]) -> Result<Vec<HeaderValue>, KycError> {
+    origins
+        .iter()
+        .map(|origin| {
+            HeaderValue::from_str(origin)
+                .map_err(|_| KycError::InvalidInput(format!("Invalid origin: {origin}")))
+        })
+        .collect()
+}
+
+/// Logs the effective configuration, so misconfiguration is visible in the deployment logs.
+fn log_startup_summary(config: &config::Config) {
+    let features_enabled = [
+        ("registration", config.registration_token.is_some()),
+        ("registration_links", config.registration_link.is_some()),
+        ("airtable_webhook", config.airtable_webhook_token.is_some()),
+        ("admin", config.admin_secret.is_some()),
+        ("expiry_notifications", !config.webhook_urls.is_empty()),
+        ("schema_drift_alerts", config.alert_webhook_url.is_some()),
+        (
+            "provider_health_check",
+            config.health_check_account_id.is_some(),
+        ),
+        (
+            "follow_linked_records",
+            config.airtable_follow_linked_records,
+        ),
+        ("auto_resubmit_on_expiry", config.auto_resubmit_on_expiry),
+        ("airtable_writeback", config.airtable_writeback),
+        ("require_tx_proof", config.require_tx_proof),
+        (
+            "kyc_bypass_accounts",
+            !config.kyc_bypass_accounts.is_empty(),
+        ),
+        ("simulated_chaos", config.chaos.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(feature, enabled)| enabled.then_some(feature))
+    .collect::<Vec<_>>();
+    let api_key_suffix = config
+        .airtable_api_key
+        .get(config.airtable_api_key.len().saturating_sub(4)..)
+        .unwrap_or_default();
+
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        provider_type = "airtable",
+        airtable_base_url = %config.airtable_base_url,
+        airtable_base_id = %config.airtable_base_id,
+        near_network = %config.near_network,
+        airtable_view_name = %config.airtable_view_name,
+        airtable_max_records = config.airtable_max_records,
+        airtable_api_key = %format!("****{api_key_suffix}"),
+        cors_mode = if config.cors_allowed_origins.is_empty() { "any" } else { "list" },
+        ?features_enabled,
+        "Starting NEAR DevHub KYC proxy"
+    );
+}
+
+/// Installs the log subscriber, which the Shuttle runtime leaves to the service so the format
+/// can depend on the secrets. `RUST_LOG` overrides the default filter.
+fn init_tracing(log_format: config::LogFormat) {
+    use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+    let registry = tracing_subscriber::registry().with(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,shuttle=trace")),
+    );
+    // Fails when a subscriber is already installed, which is fine
+    let _ = match log_format {
+        config::LogFormat::Plain => registry.with(fmt::layer().without_time()).try_init(),
+        config::LogFormat::Ecs => registry
+            .with(fmt::layer().event_format(ecs_log::EcsFormat))
+            .try_init(),
+    };
+}
+
+/// Builds the service out of the secrets, starting the background tasks it needs.
+fn build_app(
+    secret_store: &dyn config::SecretStoreExt,
+) -> Result<Router, Vec<config::ConfigError>> {
+    let config = config::Config::from_secrets(secret_store)?;
+    init_tracing(config.log_format);
+    log_startup_summary(&config);
+
+    let app_state = AppState::builder().with_config(config).build();
+
+    if !app_state.config.webhook_urls.is_empty() {
+        tokio::spawn(expiry::run_expiry_notifications(app_state.clone()));
+        tokio::spawn(expiry::run_webhook_deliveries(app_state.clone()));
+    }
+    if let Some(alert_webhook_url) = app_state.config.alert_webhook_url.clone() {
+        tokio::spawn(schema_drift::run_schema_drift_check(
+            app_state.clone(),
+            alert_webhook_url,
+        ));
+    }
+    if let Some(path) = app_state.config.kyc_cache_persist_path.clone() {
+        cache_persist::load_kyc_cache(&app_state, &path);
+        tokio::spawn(cache_persist::run_kyc_cache_persistence(
+            app_state.clone(),
+            path,
+        ));
+    }
+    if let Some(path) = app_state.config.ownership_nonce_persist_path.clone() {
+        nonce_persist::load_ownership_nonces(&app_state, &path);
+        tokio::spawn(nonce_persist::run_ownership_nonce_persistence(
+            app_state.clone(),
+            path,
+        ));
+    }
+    if let Some(path) = app_state.config.fallback_static_file.clone() {
+        tokio::spawn(fallback_file::run_fallback_file_reload(
+            app_state.clone(),
+            path,
+        ));
+    }
+    if let Some(account_id) = app_state.config.health_check_account_id.clone() {
+        tokio::spawn(provider_health::run_provider_health_check(
+            app_state.clone(),
+            account_id,
+        ));
+    }
+
+    let router = routes::router(app_state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            audit::audit_kyc_requests,
+        ))
+        .layer(
+            ServiceBuilder::new().layer(
+                CorsLayer::new()
+                    // allow `GET`, `POST` and `DELETE` (the erasure endpoint) when accessing the
+                    // resource
+                    .allow_methods([Method::GET, Method::POST, Method::DELETE])
+                    .allow_headers([
+                        axum::http::header::CONTENT_TYPE,
+                        axum::http::HeaderName::from_static("x-api-key"),
+                    ])
+                    // allow requests from the configured origins (any origin when not configured),
+                    // the list is read on every request so it can be updated at runtime
+                    .allow_origin(AllowOrigin::predicate({
+                        let app_state = app_state.clone();
+                        move |origin, _| {
+                            let allowed_origins = app_state.cors_allowed_origins.read().unwrap();
+                            allowed_origins.is_empty() || allowed_origins.contains(origin)
+                        }
+                    })),
+            ),
+        )
+        .with_state(app_state);
+
+    Ok(router)
+}
+
+#[shuttle_runtime::main]
+async fn main(#[shuttle_runtime::Secrets] secret_store: SecretStore) -> shuttle_axum::ShuttleAxum {
+    match build_app(&secret_store) {
+        Ok(router) => Ok(router.into()),
+        Err(errors) => {
+            eprintln!("Invalid configuration:");
+            for error in errors {
+                eprintln!("  {error}");
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::{
+        config::{Config, HashMapSecretStore},
+        mock_airtable::{self, MockAirtable},
+    };
+
+    #[tokio::test]
+    async fn build_app_reports_missing_airtable_api_key() {
+        let errors = crate::build_app(&HashMapSecretStore::default())
+            .expect_err("the Airtable API key is required");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, "AIRTABLE_API_KEY");
+        assert_eq!(errors[0].message, "was not found");
+    }
+
+    #[tokio::test]
+    async fn build_app_accepts_complete_secrets() {
+        let secret_store = HashMapSecretStore::default()
+            .with("AIRTABLE_API_KEY", "key")
+            .with("NEAR_NETWORK", "testnet")
+            .with("AIRTABLE_TESTNET_BASE_ID", "appTestnet");
+        assert!(crate::build_app(&secret_store).is_ok());
+    }
+
+    #[tokio::test]
+    async fn build_app_rejects_bypass_accounts_in_production() {
+        let secret_store = HashMapSecretStore::default()
+            .with("AIRTABLE_API_KEY", "key")
+            .with("KYC_BYPASS_ACCOUNTS", r#"{"test.near": "APPROVED"}"#);
+        let errors = crate::build_app(&secret_store)
+            .expect_err("the bypass accounts are only allowed outside of production");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, "ENVIRONMENT");
+        assert_eq!(
+            errors[0].message,
+            "KYC_BYPASS_ACCOUNTS is not allowed in production"
+        );
+
+        let secret_store = secret_store.with("ENVIRONMENT", "development");
+        assert!(crate::build_app(&secret_store).is_ok());
+    }
+
+    #[tokio::test]
+    async fn approved_account_is_looked_up_in_airtable() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+
+        let (status, body) = mock_airtable::get(&airtable.router(), "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["account_id"], "alice.near");
+        assert_eq!(body["kyc_status"], "APPROVED");
+
+        let requests = airtable.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, axum::http::Method::GET);
+        assert_eq!(
+            requests[0].path,
+            format!("/v0/{}/devhub_kyc", Config::default().airtable_base_id)
+        );
+        assert!(requests[0].query.contains("filterByFormula="));
+        assert_eq!(
+            requests[0].authorization.as_deref(),
+            Some("Bearer test-key")
+        );
+    }
+
+    #[tokio::test]
+    async fn lookups_request_the_configured_max_records() {
+        let airtable = MockAirtable::start().await;
+        mock_airtable::get(&airtable.router(), "/v1/kyc/alice.near").await;
+        let router =
+            airtable.router_with(airtable.secret_store().with("AIRTABLE_MAX_RECORDS", "250"));
+        mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+
+        let requests = airtable.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].query_parameter("maxRecords").as_deref(),
+            Some("100")
+        );
+        assert_eq!(
+            requests[1].query_parameter("maxRecords").as_deref(),
+            Some("250")
+        );
+    }
+
+    #[tokio::test]
+    async fn account_without_record_is_not_submitted() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+
+        let (status, body) = mock_airtable::get(&airtable.router(), "/v1/kyc/bob.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "NOT_SUBMITTED");
+    }
+
+    #[tokio::test]
+    async fn long_formula_is_sent_in_the_body() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = airtable.router_with(airtable.secret_store().with("MAX_URL_LENGTH", "10"));
+
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+
+        let requests = airtable.requests();
+        assert_eq!(requests[0].method, axum::http::Method::POST);
+        assert!(requests[0].path.ends_with("/devhub_kyc/listRecords"));
+        assert_eq!(
+            requests[0].body["filterByFormula"],
+            "{near_wallet}='alice.near'"
+        );
+    }
+
+    #[tokio::test]
+    async fn last_known_status_is_served_when_airtable_fails() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = airtable.router_with(airtable.secret_store().with("KYC_CACHE_TTL_SECS", "0"));
+
+        let (_, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(body.get("stale"), None);
+
+        airtable.fail_with(StatusCode::INTERNAL_SERVER_ERROR);
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+        assert_eq!(body["stale"], true);
+    }
+
+    #[tokio::test]
+    async fn accounts_without_record_have_no_last_known_status() {
+        let airtable = MockAirtable::start().await;
+        let router = airtable.router_with(airtable.secret_store().with("KYC_CACHE_TTL_SECS", "0"));
+
+        let (_, body) = mock_airtable::get(&router, "/v1/kyc/bob.near").await;
+        assert_eq!(body["kyc_status"], "NOT_SUBMITTED");
+
+        airtable.fail_with(StatusCode::INTERNAL_SERVER_ERROR);
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/bob.near").await;
+        assert!(status.is_server_error());
+    }
+
+    fn registration_request(account_id: &str) -> axum::http::Request<axum::body::Body> {
+        axum::http::Request::post("/v1/kyc/register")
+            .header("Content-Type", "application/json")
+            .header("X-Registration-Token", "registration-token")
+            .body(axum::body::Body::from(
+                serde_json::json!({"account_id": account_id, "chain": "NEAR"}).to_string(),
+            ))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn registration_creates_not_submitted_record() {
+        let airtable = MockAirtable::start().await;
+        let router = airtable.router_with(
+            airtable
+                .secret_store()
+                .with("REGISTRATION_TOKEN", "registration-token"),
+        );
+
+        let (status, body) = mock_airtable::send(&router, registration_request("alice.near")).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["kyc_status"], "NOT_SUBMITTED");
+
+        let requests = airtable.requests();
+        assert_eq!(requests.len(), 2);
+        // The existence check is not limited to the configured view
+        assert!(!requests[0].query.contains("view="));
+        assert_eq!(requests[1].method, axum::http::Method::POST);
+        assert!(requests[1].path.ends_with("/devhub_kyc"));
+        assert_eq!(
+            requests[1].body,
+            serde_json::json!({"fields": {
+                "near_wallet": "alice.near",
+                "Owner Verification Status": "Not Submitted",
+            }})
+        );
+
+        // The created record reads as not submitted
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "NOT_SUBMITTED");
+    }
+
+    #[tokio::test]
+    async fn registration_of_account_with_linked_wallet_conflicts() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed_record(serde_json::json!({
+            "near_wallet": "alice.near",
+            "status": "approved",
+            "approval_standing": "active",
+            "chain": "ETH",
+            "wallet_address": "0x52908400098527886E0F7030069857D2E4169EE7",
+        }));
+        let router = airtable.router_with(
+            airtable
+                .secret_store()
+                .with("REGISTRATION_TOKEN", "registration-token"),
+        );
+
+        let (status, _) = mock_airtable::send(&router, registration_request("alice.near")).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(airtable.records().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn registration_of_existing_account_conflicts() {
+        let airtable = MockAirtable::start().await;
+        airtable
+            .seed_record(serde_json::json!({"near_wallet": "alice.near", "status": "rejected"}));
+        let router = airtable.router_with(
+            airtable
+                .secret_store()
+                .with("REGISTRATION_TOKEN", "registration-token"),
+        );
+
+        let (status, _) = mock_airtable::send(&router, registration_request("alice.near")).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(airtable.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn erasure_removes_the_kept_data() {
+        let airtable = MockAirtable::start().await;
+        let (state, router) = airtable.app(airtable.config());
+        let account_id: near_account_id::AccountId = "alice.near".parse().unwrap();
+        let wallet_address = crate::wallet::WalletAddress::from(account_id.clone());
+        let view = &state.config.airtable_view_name;
+        state
+            .kyc_cache
+            .insert(&wallet_address, view, crate::KycStatus::Approved);
+        state
+            .stale_kyc_cache
+            .insert(&wallet_address, view, crate::KycStatus::Approved);
+        state
+            .timeline
+            .record(&account_id, crate::timeline::TimelineEvent::CacheEvicted);
+        let nonce = state.ownership_nonces.issue(account_id.clone());
+
+        let mut request = mock_airtable::signed_request(
+            axum::http::Method::DELETE,
+            "/v1/kyc/alice.near?operator=dpo%40example.com",
+            "",
+        );
+        request
+            .headers_mut()
+            .insert("X-Forwarded-For", "203.0.113.7".parse().unwrap());
+        let (status, _) = mock_airtable::send(&router, request).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        assert!(state.kyc_cache.entries(&wallet_address).is_empty());
+        assert!(state.stale_kyc_cache.entries(&wallet_address).is_empty());
+        assert!(state.timeline.get(&account_id).is_empty());
+        assert!(!state.ownership_nonces.consume(&account_id, &nonce));
+        // The Airtable records are kept unless `GDPR_DELETE_FROM_AIRTABLE` is enabled
+        assert_eq!(airtable.call_count(), 0);
+
+        let deletion_log = state.deletion_log.entries();
+        assert_eq!(deletion_log.len(), 1);
+        assert_eq!(deletion_log[0].account_id, account_id);
+        assert_eq!(deletion_log[0].operator, "dpo@example.com");
+        assert_eq!(deletion_log[0].deleted_airtable_records, 0);
+        assert_eq!(
+            deletion_log[0].requester_ip.as_deref(),
+            Some("203.0.113.0/24")
+        );
+    }
+
+    #[tokio::test]
+    async fn erasure_deletes_airtable_records_when_enabled() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        airtable.seed("alice.near", "rejected");
+        airtable.seed("bob.near", "approved");
+        let (_, router) = airtable.app(Config {
+            gdpr_delete_from_airtable: true,
+            ..airtable.config()
+        });
+
+        let (status, _) = mock_airtable::send(
+            &router,
+            mock_airtable::signed_request(axum::http::Method::DELETE, "/v1/kyc/alice.near", ""),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let records = airtable.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["near_wallet"], "bob.near");
+
+        let (status, body) = mock_airtable::send(
+            &router,
+            mock_airtable::signed_request(axum::http::Method::GET, "/admin/deletion-log", ""),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["account_id"], "alice.near");
+        assert_eq!(body[0]["operator"], "admin");
+        assert_eq!(body[0]["deleted_airtable_records"], 2);
+    }
+
+    fn batch_request(account_id: &str) -> axum::http::Request<axum::body::Body> {
+        axum::http::Request::post("/v1/kyc/batch")
+            .header("Content-Type", "application/json")
+            .body(axum::body::Body::from(
+                serde_json::json!({"account_ids": [account_id]}).to_string(),
+            ))
+            .unwrap()
+    }
+
+    fn circuit_states(
+        state: &crate::AppState,
+    ) -> (
+        crate::circuit_breaker::CircuitState,
+        crate::circuit_breaker::CircuitState,
+    ) {
+        let circuit_breakers = &state.airtable_circuit_breakers;
+        (
+            circuit_breakers[crate::circuit_breaker::LOOKUP_CIRCUIT].state(),
+            circuit_breakers[crate::circuit_breaker::BATCH_CIRCUIT].state(),
+        )
+    }
+
+    fn tripping_config(airtable: &MockAirtable) -> Config {
+        Config {
+            circuit_breaker_failure_threshold: 1,
+            circuit_breaker_open_secs: 60,
+            kyc_cache_ttl_secs: 0,
+            ..airtable.config()
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_failures_do_not_open_the_lookup_circuit() {
+        use crate::circuit_breaker::CircuitState;
+
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let (state, router) = airtable.app(tripping_config(&airtable));
+
+        airtable.fail_with(StatusCode::INTERNAL_SERVER_ERROR);
+        let (_, body) = mock_airtable::send(&router, batch_request("alice.near")).await;
+        assert!(body[0]["error"].is_string());
+        assert!(circuit_states(&state) == (CircuitState::Closed, CircuitState::Open));
+
+        airtable.recover();
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+        // The open batch circuit still rejects the batches without calling Airtable
+        let calls = airtable.call_count();
+        mock_airtable::send(&router, batch_request("alice.near")).await;
+        assert_eq!(airtable.call_count(), calls);
+    }
+
+    #[tokio::test]
+    async fn lookup_failures_do_not_open_the_batch_circuit() {
+        use crate::circuit_breaker::CircuitState;
+
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let (state, router) = airtable.app(tripping_config(&airtable));
+
+        airtable.fail_with(StatusCode::INTERNAL_SERVER_ERROR);
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert!(status.is_server_error());
+        assert!(circuit_states(&state) == (CircuitState::Open, CircuitState::Closed));
+
+        airtable.recover();
+        let (_, body) = mock_airtable::send(&router, batch_request("alice.near")).await;
+        assert_eq!(body[0]["kyc_status"], "APPROVED");
+        // The open lookup circuit serves the status the batch found without calling Airtable
+        let calls = airtable.call_count();
+        let (_, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(body["stale"], true);
+        assert_eq!(airtable.call_count(), calls);
+    }
+
+    #[tokio::test]
+    async fn nonces_are_rate_limited_by_requester() {
+        let airtable = MockAirtable::start().await;
+        let router = airtable.router();
+        let issue_nonce = |requester_ip: &str| {
+            mock_airtable::send(
+                &router,
+                axum::http::Request::get("/v1/kyc/nonce/alice.near")
+                    .header("X-Forwarded-For", requester_ip)
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        for _ in 0..crate::MAX_NONCES_PER_MINUTE {
+            let (status, body) = issue_nonce("203.0.113.1").await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(body["nonce"].is_string());
+        }
+        assert_eq!(
+            issue_nonce("203.0.113.1").await.0,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(issue_nonce("203.0.113.2").await.0, StatusCode::OK);
+    }
+
+    async fn rotate_api_key(router: &axum::Router, api_key: &str) {
+        let (status, _) = mock_airtable::send(
+            router,
+            mock_airtable::signed_request(
+                axum::http::Method::POST,
+                "/admin/rotate-key",
+                &serde_json::json!({"new_airtable_api_key": api_key}).to_string(),
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn new_key_is_used_after_rotation() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let (_, router) = airtable.app(Config {
+            kyc_cache_ttl_secs: 0,
+            ..airtable.config()
+        });
+
+        mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        rotate_api_key(&router, "new-key").await;
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let authorizations = airtable
+            .requests()
+            .into_iter()
+            .map(|request| request.authorization.unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(authorizations, ["Bearer test-key", "Bearer new-key"]);
+    }
+
+    #[tokio::test]
+    async fn previous_key_is_tried_during_the_grace_period() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        airtable.reject_api_key("inactive-key");
+        let (_, router) = airtable.app(Config {
+            kyc_cache_ttl_secs: 0,
+            ..airtable.config()
+        });
+
+        rotate_api_key(&router, "inactive-key").await;
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+        let authorizations = airtable
+            .requests()
+            .into_iter()
+            .map(|request| request.authorization.unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(authorizations, ["Bearer inactive-key", "Bearer test-key"]);
+    }
+
+    #[tokio::test]
+    async fn previous_key_is_dropped_after_the_grace_period() {
+        let airtable = MockAirtable::start().await;
+        airtable.reject_api_key("inactive-key");
+        let (_, router) = airtable.app(Config {
+            key_rotation_grace_period_secs: 0,
+            ..airtable.config()
+        });
+
+        rotate_api_key(&router, "inactive-key").await;
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert!(status.is_server_error());
+        assert_eq!(airtable.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn simulated_status_change_notifies_the_webhooks() {
+        let airtable = MockAirtable::start().await;
+        let (state, router) = airtable.app(Config {
+            webhook_urls: vec![format!("{}/webhook", airtable.base_url())],
+            environment: crate::config::Environment::Test,
+            ..airtable.config()
+        });
+        tokio::spawn(crate::expiry::run_webhook_deliveries(state.clone()));
+
+        let (status, body) = mock_airtable::send(
+            &router,
+            mock_airtable::signed_request(
+                axum::http::Method::POST,
+                "/admin/simulate/status-change",
+                r#"{"account_id": "alice.near", "from_status": "PENDING", "to_status": "APPROVED"}"#,
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+
+        let webhook = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                if let Some(request) = airtable.requests().into_iter().next() {
+                    return request;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the webhook is delivered");
+        assert_eq!(webhook.path, "/webhook");
+        assert_eq!(webhook.body["event"], "kyc_status_changed");
+        assert_eq!(webhook.body["account_id"], "alice.near");
+        assert_eq!(webhook.body["from_status"], "PENDING");
+        assert_eq!(webhook.body["to_status"], "APPROVED");
+        assert!(webhook.body["changed_at"].is_string());
+    }
+
+    #[tokio::test]
+    async fn cached_status_is_not_looked_up_again() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = airtable.router();
+
+        mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+        assert_eq!(airtable.call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn account_id_in_the_path_is_decoded_once() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = airtable.router();
+
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice%2Enear").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["account_id"], "alice.near");
+
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice%252Enear").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn expired_listing_includes_records_without_a_standing() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        airtable.seed_record(serde_json::json!({
+            "near_wallet": "bob.near",
+            "status": "approved",
+            "approval_standing": "expired",
+        }));
+        airtable
+            .seed_record(serde_json::json!({"near_wallet": "carol.near", "status": "approved"}));
+        // Registered accounts which never submitted have nothing to re-verify
+        airtable.seed_record(serde_json::json!({"near_wallet": "dave.near"}));
+        let (_, router) = airtable.app(airtable.config());
+
+        let (status, body) = mock_airtable::send(
+            &router,
+            mock_airtable::signed_request(axum::http::Method::GET, "/v1/kyc/expired", ""),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let account_ids = body["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["account_id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(account_ids, ["bob.near", "carol.near"]);
+    }
+
+    #[tokio::test]
+    async fn failed_refresh_drops_the_last_known_status() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let (state, router) = airtable.app(airtable.config());
+        mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(state.stale_kyc_cache.len(), 1);
+
+        airtable.fail_with(StatusCode::INTERNAL_SERVER_ERROR);
+        let (status, _) = mock_airtable::send(
+            &router,
+            mock_airtable::signed_request(
+                axum::http::Method::POST,
+                "/v1/kyc/alice.near/refresh",
+                "",
+            ),
+        )
+        .await;
+        assert!(status.is_server_error());
+        assert_eq!(state.stale_kyc_cache.len(), 0);
+
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert!(status.is_server_error());
+    }
+
+    #[tokio::test]
+    async fn response_fields_are_selected_last() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = airtable.router();
+
+        let (status, body) =
+            mock_airtable::get(&router, "/v1/kyc/alice.near?fields=kyc_status").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!({"kyc_status": "APPROVED"}));
+
+        let (_, body) = mock_airtable::get(
+            &router,
+            "/v1/kyc/alice.near?fields=kyc_status,schema_version,stale",
+        )
+        .await;
+        assert_eq!(
+            body,
+            serde_json::json!({"kyc_status": "APPROVED", "schema_version": crate::API_SCHEMA_VERSION})
+        );
+
+        let (_, body) = mock_airtable::send(
+            &router,
+            axum::http::Request::get("/v1/kyc/alice.near?fields=verified")
+                .header(axum::http::header::ACCEPT, crate::KYC_V2_MEDIA_TYPE)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(body, serde_json::json!({"verified": true}));
+    }
+
+    #[tokio::test]
+    async fn unavailable_dependencies_are_named() {
+        use axum::response::IntoResponse;
+
+        let response = crate::KycError::DependencyUnavailable("near_rpc").into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({"error_code": "DEPENDENCY_UNAVAILABLE", "dependency": "near_rpc"})
+        );
+    }
+
+    #[test]
+    fn overall_status_ignores_the_chains_without_records() {
+        use crate::{config::AllChainsPolicy, KycStatus::*};
+
+        let cases = [
+            ([Approved, NotSubmitted, NotSubmitted], Approved, Approved),
+            ([Pending, Approved, Rejected], Approved, Rejected),
+            ([Expired, Pending, NotSubmitted], Pending, Expired),
+            (
+                [NotSubmitted, NotSubmitted, NotSubmitted],
+                NotSubmitted,
+                NotSubmitted,
+            ),
+        ];
+        for (kyc_statuses, most_permissive, most_restrictive) in cases {
+            assert_eq!(
+                AllChainsPolicy::MostPermissive.overall_kyc_status(&kyc_statuses),
+                most_permissive
+            );
+            assert_eq!(
+                AllChainsPolicy::MostRestrictive.overall_kyc_status(&kyc_statuses),
+                most_restrictive
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn all_chains_lookup_includes_the_linked_wallets() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "pending");
+        airtable.seed_record(serde_json::json!({
+            "near_wallet": "alice.near",
+            "status": "approved",
+            "approval_standing": "active",
+            "chain": "ETH",
+            "wallet_address": "0x52908400098527886E0F7030069857D2E4169EE7",
+        }));
+        airtable.seed_record(serde_json::json!({
+            "near_wallet": "alice.near",
+            "status": "rejected",
+            "approval_standing": "active",
+            "chain": "SOL",
+            "wallet_address": "So11111111111111111111111111111111111111112",
+        }));
+        let router = airtable.router();
+
+        let (status, body) =
+            mock_airtable::get(&router, "/v1/kyc/alice.near?all_chains=true").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "account_id": "alice.near",
+                "near": {"kyc_status": "PENDING"},
+                "eth": {
+                    "wallet_address": "0x52908400098527886e0f7030069857d2e4169ee7",
+                    "kyc_status": "APPROVED",
+                },
+                "sol": {
+                    "wallet_address": "So11111111111111111111111111111111111111112",
+                    "kyc_status": "REJECTED",
+                },
+                "overall": "APPROVED",
+            })
+        );
+
+        // The records of the other chains do not count for the NEAR account itself
+        let (_, body) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(body["kyc_status"], "PENDING");
+
+        let router = airtable.router_with(
+            airtable
+                .secret_store()
+                .with("ALL_CHAINS_POLICY", "MostRestrictive"),
+        );
+        let (_, body) = mock_airtable::get(&router, "/v1/kyc/bob.near?all_chains=true").await;
+        assert_eq!(body["overall"], "NOT_SUBMITTED");
+        let (_, body) = mock_airtable::get(&router, "/v1/kyc/alice.near?all_chains=true").await;
+        assert_eq!(body["overall"], "REJECTED");
+    }
+
+    async fn get_admin_kyc_status(router: &axum::Router, account_id: &str) -> serde_json::Value {
+        let (status, body) = mock_airtable::send(
+            router,
+            mock_airtable::signed_request(
+                axum::http::Method::GET,
+                &format!("/admin/kyc/{account_id}"),
+                "",
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        body
+    }
+
+    #[tokio::test]
+    async fn admin_lookup_skips_records_without_the_required_fields() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let record_id = airtable.seed_record(serde_json::json!({
+            "near_wallet": "alice.near",
+            "status": "pending",
+            "approval_standing": "active",
+            "program": "grants",
+        }));
+        let (_, router) = airtable.app(Config {
+            required_airtable_fields: vec!["program".to_string()],
+            ..airtable.config()
+        });
+
+        let body = get_admin_kyc_status(&router, "alice.near").await;
+        assert_eq!(body["kyc_status"], "PENDING");
+        assert_eq!(body["airtable_record_id"], record_id);
+        assert_eq!(body["raw_fields"]["program"], "grants");
+
+        // The admin lookup is not served from the cache
+        get_admin_kyc_status(&router, "alice.near").await;
+        assert_eq!(airtable.call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn admin_lookup_reports_the_bypass_status() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("tester.near", "rejected");
+        let (_, router) = airtable.app(Config {
+            kyc_bypass_accounts: [("tester.near".parse().unwrap(), crate::KycStatus::Approved)]
+                .into(),
+            ..airtable.config()
+        });
+
+        let body = get_admin_kyc_status(&router, "tester.near").await;
+        assert_eq!(body["kyc_status"], "APPROVED");
+        assert_eq!(body["airtable_record_id"], serde_json::Value::Null);
+        assert_eq!(airtable.call_count(), 0);
+    }
+
+    /// Answers every query with the same records, without an Airtable API.
+    struct FakeAirtableProvider {
+        response: Result<Vec<serde_json::Value>, crate::KycError>,
+        queries: std::sync::Mutex<Vec<Vec<(String, String)>>>,
+    }
+
+    impl FakeAirtableProvider {
+        fn new(response: Result<Vec<serde_json::Value>, crate::KycError>) -> Self {
+            Self {
+                response,
+                queries: Default::default(),
+            }
+        }
+    }
+
+    #[axum::async_trait]
+    impl crate::airtable_client::AirtableProvider for FakeAirtableProvider {
+        async fn query_records(
+            &self,
+            _state: &crate::AppState,
+            query: &[(&str, &str)],
+        ) -> Result<serde_json::Value, crate::KycError> {
+            self.queries.lock().unwrap().push(
+                query
+                    .iter()
+                    .map(|&(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            );
+            let records = self.response.clone()?;
+            Ok(serde_json::json!({ "records": records }))
+        }
+    }
+
+    fn fake_record(id: &str, created_time: &str, fields: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({ "id": id, "createdTime": created_time, "fields": fields })
+    }
+
+    async fn fake_lookup(
+        provider: std::sync::Arc<FakeAirtableProvider>,
+        config: Config,
+    ) -> Result<crate::AirtableKycLookup, crate::KycError> {
+        let state = crate::AppState::builder()
+            .with_config(config)
+            .with_airtable_provider(provider)
+            .build();
+        crate::lookup_airtable_kyc_status(
+            &state,
+            &"alice.near".parse().unwrap(),
+            &state.config.airtable_view_name,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn active_approval_wins_over_newer_submissions() {
+        let provider = std::sync::Arc::new(FakeAirtableProvider::new(Ok(vec![
+            fake_record(
+                "recApproved",
+                "2024-01-01T00:00:00.000Z",
+                serde_json::json!({
+                    "near_wallet": "alice.near",
+                    "status": "approved",
+                    "approval_standing": "active",
+                }),
+            ),
+            fake_record(
+                "recPending",
+                "2024-06-01T00:00:00.000Z",
+                serde_json::json!({ "near_wallet": "alice.near", "status": "pending" }),
+            ),
+        ])));
+
+        let lookup = fake_lookup(provider.clone(), Config::default())
+            .await
+            .unwrap();
+        assert_eq!(lookup.kyc_status, crate::KycStatus::Approved);
+        assert_eq!(lookup.record.unwrap().id, "recApproved");
+        assert_eq!(lookup.raw_fields["status"], "approved");
+
+        let queries = provider.queries.lock().unwrap();
+        assert_eq!(queries.len(), 1);
+        assert!(queries[0]
+            .iter()
+            .any(|(key, value)| key == "filterByFormula" && value.contains("alice.near")));
+    }
+
+    #[tokio::test]
+    async fn most_recent_policy_picks_the_newest_record() {
+        let provider = std::sync::Arc::new(FakeAirtableProvider::new(Ok(vec![
+            fake_record(
+                "recApproved",
+                "2024-01-01T00:00:00.000Z",
+                serde_json::json!({
+                    "near_wallet": "alice.near",
+                    "status": "approved",
+                    "approval_standing": "active",
+                }),
+            ),
+            fake_record(
+                "recRejected",
+                "2024-06-01T00:00:00.000Z",
+                serde_json::json!({
+                    "near_wallet": "alice.near",
+                    "status": "rejected",
+                    "approval_standing": "active",
+                }),
+            ),
+        ])));
+
+        let lookup = fake_lookup(
+            provider,
+            Config {
+                tie_breaking_policy: crate::config::TieBreakingPolicy::MostRecent,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(lookup.kyc_status, crate::KycStatus::Rejected);
+        assert_eq!(lookup.record.unwrap().id, "recRejected");
+    }
+
+    #[tokio::test]
+    async fn expired_approval_standing_expires_the_status() {
+        let provider = std::sync::Arc::new(FakeAirtableProvider::new(Ok(vec![fake_record(
+            "recExpired",
+            "2024-01-01T00:00:00.000Z",
+            serde_json::json!({
+                "near_wallet": "alice.near",
+                "status": "approved",
+                "approval_standing": "expired",
+            }),
+        )])));
+
+        let lookup = fake_lookup(provider, Config::default()).await.unwrap();
+        assert_eq!(lookup.kyc_status, crate::KycStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn records_without_required_fields_or_of_other_chains_are_skipped() {
+        let provider = std::sync::Arc::new(FakeAirtableProvider::new(Ok(vec![
+            fake_record(
+                "recWithoutProgram",
+                "2024-01-01T00:00:00.000Z",
+                serde_json::json!({
+                    "near_wallet": "alice.near",
+                    "status": "approved",
+                    "approval_standing": "active",
+                }),
+            ),
+            fake_record(
+                "recEth",
+                "2024-02-01T00:00:00.000Z",
+                serde_json::json!({
+                    "near_wallet": "alice.near",
+                    "status": "approved",
+                    "approval_standing": "active",
+                    "chain": "ETH",
+                    "program": "grants",
+                }),
+            ),
+        ])));
+
+        let lookup = fake_lookup(
+            provider,
+            Config {
+                required_airtable_fields: vec!["program".to_string()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(lookup.kyc_status, crate::KycStatus::NotSubmitted);
+        assert!(lookup.record.is_none());
+        assert_eq!(lookup.raw_fields, serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn provider_errors_fail_the_lookup() {
+        let provider = std::sync::Arc::new(FakeAirtableProvider::new(Err(
+            crate::KycError::ServiceUnavailable,
+        )));
+        assert!(matches!(
+            fake_lookup(provider, Config::default()).await,
+            Err(crate::KycError::ServiceUnavailable)
+        ));
+
+        let provider = std::sync::Arc::new(FakeAirtableProvider::new(Ok(vec![
+            serde_json::json!({ "id": "recBroken", "fields": { "status": 42 } }),
+        ])));
+        assert!(matches!(
+            fake_lookup(provider, Config::default()).await,
+            Err(crate::KycError::DeserializationError)
+        ));
+    }
 }
@@ -0,0 +1,140 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+const AIRTABLE_DURATION_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+#[derive(Default)]
+struct Histogram {
+    /// Cumulative counts of observations falling into each of [`AIRTABLE_DURATION_BUCKETS`].
+    bucket_counts: [u64; AIRTABLE_DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, count) in AIRTABLE_DURATION_BUCKETS
+            .iter()
+            .zip(self.bucket_counts.iter_mut())
+        {
+            if value <= *bucket {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Metrics exposed in the Prometheus text format on `GET /metrics`.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    airtable_response_duration: Mutex<BTreeMap<String, Histogram>>,
+    user_agent_blocked_total: AtomicU64,
+}
+
+impl Metrics {
+    /// Records an Airtable call, labelled by the response status code (server errors are grouped
+    /// as `5xx`, and calls which got no response as `error`).
+    pub(crate) fn observe_airtable_response(
+        &self,
+        status: Option<reqwest::StatusCode>,
+        duration: Duration,
+    ) {
+        let status_code = match status {
+            Some(status) if status.is_server_error() => "5xx".to_string(),
+            Some(status) => status.as_u16().to_string(),
+            None => "error".to_string(),
+        };
+        self.airtable_response_duration
+            .lock()
+            .unwrap()
+            .entry(status_code)
+            .or_default()
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Total number of Airtable calls and how many of them failed (anything but a 2xx response).
+    pub(crate) fn airtable_call_totals(&self) -> (u64, u64) {
+        self.airtable_response_duration.lock().unwrap().iter().fold(
+            (0, 0),
+            |(calls, errors), (status_code, histogram)| {
+                let failed = !status_code.starts_with('2');
+                (
+                    calls + histogram.count,
+                    errors + if failed { histogram.count } else { 0 },
+                )
+            },
+        )
+    }
+
+    pub(crate) fn increment_user_agent_blocked(&self) {
+        self.user_agent_blocked_total
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut output = String::new();
+        writeln!(
+            output,
+            "# HELP airtable_response_duration_seconds Duration of Airtable API calls."
+        )
+        .unwrap();
+        writeln!(
+            output,
+            "# TYPE airtable_response_duration_seconds histogram"
+        )
+        .unwrap();
+        for (status_code, histogram) in self.airtable_response_duration.lock().unwrap().iter() {
+            for (bucket, count) in AIRTABLE_DURATION_BUCKETS
+                .iter()
+                .zip(histogram.bucket_counts.iter())
+            {
+                writeln!(
+                    output,
+                    "airtable_response_duration_seconds_bucket{{status_code=\"{status_code}\",le=\"{bucket}\"}} {count}"
+                )
+                .unwrap();
+            }
+            writeln!(
+                output,
+                "airtable_response_duration_seconds_bucket{{status_code=\"{status_code}\",le=\"+Inf\"}} {}",
+                histogram.count
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "airtable_response_duration_seconds_sum{{status_code=\"{status_code}\"}} {}",
+                histogram.sum
+            )
+            .unwrap();
+            writeln!(
+                output,
+                "airtable_response_duration_seconds_count{{status_code=\"{status_code}\"}} {}",
+                histogram.count
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            output,
+            "# HELP user_agent_blocked_total Requests rejected because of a blocked User-Agent."
+        )
+        .unwrap();
+        writeln!(output, "# TYPE user_agent_blocked_total counter").unwrap();
+        writeln!(
+            output,
+            "user_agent_blocked_total {}",
+            self.user_agent_blocked_total.load(Ordering::Relaxed)
+        )
+        .unwrap();
+        output
+    }
+}
@@ -0,0 +1,424 @@
+//! In-process Airtable API for the tests, served on a random local port so the whole service can
+//! be exercised without network access:
+//!
+//! ```ignore
+//! let airtable = MockAirtable::start().await;
+//! airtable.seed("alice.near", "approved");
+//! let router = airtable.router();
+//! ```
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{Method, StatusCode},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+
+use crate::config::HashMapSecretStore;
+
+/// Admin secret of [`MockAirtable::config`].
+pub(crate) const ADMIN_SECRET: &str = "admin-secret";
+
+/// A request received by the mock.
+#[derive(Clone, Debug)]
+pub(crate) struct MockRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: String,
+    pub authorization: Option<String>,
+    pub body: serde_json::Value,
+}
+
+impl MockRequest {
+    /// Decoded value of the query parameter.
+    pub(crate) fn query_parameter(&self, name: &str) -> Option<String> {
+        query_parameter(&self.query, name)
+    }
+}
+
+#[derive(Clone)]
+struct MockRecord {
+    id: String,
+    table: String,
+    created_time: chrono::DateTime<chrono::Utc>,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl MockRecord {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "createdTime": self.created_time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "fields": self.fields,
+        })
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    records: Mutex<Vec<MockRecord>>,
+    requests: Mutex<Vec<MockRequest>>,
+    calls: AtomicUsize,
+    /// API keys answered with `401 Unauthorized`.
+    rejected_api_keys: Mutex<Vec<String>>,
+    /// Status every request is answered with instead of the records, if any.
+    failure: Mutex<Option<StatusCode>>,
+    /// Columns of the KYC table reported by the Meta API.
+    columns: Mutex<Vec<String>>,
+}
+
+pub(crate) struct MockAirtable {
+    base_url: String,
+    state: Arc<MockState>,
+}
+
+impl MockAirtable {
+    pub(crate) async fn start() -> Self {
+        let state = Arc::new(MockState::default());
+        *state.columns.lock().unwrap() = crate::AIRTABLE_FIELD_NAMES
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("a local port is available");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let router = Router::new().fallback(handle).with_state(state.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Self { base_url, state }
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Secrets of a service using the mock.
+    pub(crate) fn secret_store(&self) -> HashMapSecretStore {
+        HashMapSecretStore::default()
+            .with("AIRTABLE_API_KEY", "test-key")
+            .with("AIRTABLE_BASE_URL", &self.base_url)
+    }
+
+    /// The full service, using the mock.
+    pub(crate) fn router(&self) -> Router {
+        self.router_with(self.secret_store())
+    }
+
+    pub(crate) fn router_with(&self, secret_store: HashMapSecretStore) -> Router {
+        crate::build_app(&secret_store).unwrap_or_else(|errors| {
+            panic!(
+                "invalid test configuration: {}",
+                errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+    }
+
+    /// The service config, using the mock.
+    pub(crate) fn config(&self) -> crate::config::Config {
+        crate::config::Config {
+            airtable_api_key: "test-key".to_string(),
+            airtable_base_url: self.base_url.clone(),
+            admin_secret: Some(ADMIN_SECRET.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// The routes of a service with the config, along with its state for the tests to inspect.
+    pub(crate) fn app(&self, config: crate::config::Config) -> (Arc<crate::AppState>, Router) {
+        let state = crate::AppState::builder().with_config(config).build();
+        let router = crate::routes::router(state.clone()).with_state(state.clone());
+        (state, router)
+    }
+
+    /// Adds an active KYC record of the account with the Airtable `status` value.
+    pub(crate) fn seed(&self, account_id: &str, status: &str) -> String {
+        self.seed_record(serde_json::json!({
+            "near_wallet": account_id,
+            "status": status,
+            "approval_standing": "active",
+        }))
+    }
+
+    /// Adds a KYC record with the given fields, returning its ID.
+    pub(crate) fn seed_record(&self, fields: serde_json::Value) -> String {
+        self.seed_table_record(crate::AIRTABLE_TABLE, fields)
+    }
+
+    pub(crate) fn seed_table_record(&self, table: &str, fields: serde_json::Value) -> String {
+        let mut records = self.state.records.lock().unwrap();
+        let id = format!("rec{:014}", records.len() + 1);
+        records.push(MockRecord {
+            id: id.clone(),
+            table: table.to_string(),
+            created_time: chrono::Utc::now(),
+            fields: fields.as_object().cloned().unwrap_or_default(),
+        });
+        id
+    }
+
+    /// Fields of the KYC records, in creation order.
+    pub(crate) fn records(&self) -> Vec<serde_json::Map<String, serde_json::Value>> {
+        self.state
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| record.table == crate::AIRTABLE_TABLE)
+            .map(|record| record.fields.clone())
+            .collect()
+    }
+
+    /// Answers every request with the status from now on.
+    pub(crate) fn fail_with(&self, status: StatusCode) {
+        *self.state.failure.lock().unwrap() = Some(status);
+    }
+
+    /// Answers the requests authenticated with the API key with `401 Unauthorized` from now on.
+    pub(crate) fn reject_api_key(&self, api_key: &str) {
+        self.state
+            .rejected_api_keys
+            .lock()
+            .unwrap()
+            .push(format!("Bearer {api_key}"));
+    }
+
+    pub(crate) fn recover(&self) {
+        *self.state.failure.lock().unwrap() = None;
+    }
+
+    pub(crate) fn call_count(&self) -> usize {
+        self.state.calls.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn requests(&self) -> Vec<MockRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+}
+
+/// Sends the request to the service, returning the status and the JSON body (`null` when the body
+/// is not JSON).
+pub(crate) async fn send(
+    router: &Router,
+    request: axum::http::Request<axum::body::Body>,
+) -> (StatusCode, serde_json::Value) {
+    use tower::ServiceExt;
+
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+/// Admin request signed with the [`ADMIN_SECRET`].
+pub(crate) fn signed_request(
+    method: Method,
+    uri: &str,
+    body: &str,
+) -> axum::http::Request<axum::body::Body> {
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let signature = crate::signing::sign(
+        ADMIN_SECRET,
+        &timestamp,
+        method.as_str(),
+        uri,
+        body.as_bytes(),
+    );
+    axum::http::Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("X-Timestamp", timestamp)
+        .header("X-Admin-Signature", signature)
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// Sends a `GET` request to the service, see [`send`].
+pub(crate) async fn get(router: &Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    send(
+        router,
+        axum::http::Request::get(uri)
+            .body(axum::body::Body::empty())
+            .unwrap(),
+    )
+    .await
+}
+
+async fn handle(State(state): State<Arc<MockState>>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .unwrap_or_default();
+    let body = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    let query = parts.uri.query().unwrap_or_default().to_string();
+    state.calls.fetch_add(1, Ordering::SeqCst);
+    state.requests.lock().unwrap().push(MockRequest {
+        method: parts.method.clone(),
+        path: parts.uri.path().to_string(),
+        query: query.clone(),
+        authorization: parts
+            .headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        body: body.clone(),
+    });
+    let authorization = parts
+        .headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if state
+        .rejected_api_keys
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|rejected| rejected == authorization)
+    {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "AUTHENTICATION_REQUIRED"})),
+        )
+            .into_response();
+    }
+    if let Some(status) = *state.failure.lock().unwrap() {
+        return (status, Json(serde_json::json!({"error": "MOCK_FAILURE"}))).into_response();
+    }
+
+    // `/{version}/{base}/{table}[/{record}]` or `/{version}/meta/bases/{base}/tables`
+    let segments = parts.uri.path().split('/').skip(2).collect::<Vec<_>>();
+    if segments.first() == Some(&"meta") {
+        return Json(serde_json::json!({
+            "tables": [{
+                "name": crate::AIRTABLE_TABLE,
+                "fields": state
+                    .columns
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|name| serde_json::json!({"name": name}))
+                    .collect::<Vec<_>>(),
+            }],
+        }))
+        .into_response();
+    }
+    let table = percent_encoding::percent_decode_str(segments.get(1).unwrap_or(&""))
+        .decode_utf8_lossy()
+        .to_string();
+    let record_id = segments.get(2).copied();
+    let mut records = state.records.lock().unwrap();
+    match (parts.method, record_id) {
+        (Method::GET, None) => {
+            let formula = query_parameter(&query, "filterByFormula").unwrap_or_default();
+            list_records(&records, &table, &formula)
+        }
+        (Method::GET, Some(record_id)) => {
+            match records
+                .iter()
+                .find(|record| record.table == table && record.id == record_id)
+            {
+                Some(record) => Json(record.to_json()).into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+        (Method::POST, Some("listRecords")) => {
+            let formula = body["filterByFormula"].as_str().unwrap_or_default();
+            list_records(&records, &table, formula)
+        }
+        (Method::POST, None) => {
+            let record = MockRecord {
+                id: format!("rec{:014}", records.len() + 1),
+                table,
+                created_time: chrono::Utc::now(),
+                fields: body["fields"].as_object().cloned().unwrap_or_default(),
+            };
+            let response = record.to_json();
+            records.push(record);
+            Json(response).into_response()
+        }
+        (Method::PATCH, Some(record_id)) => {
+            let Some(record) = records.iter_mut().find(|record| record.id == record_id) else {
+                return StatusCode::NOT_FOUND.into_response();
+            };
+            if let Some(fields) = body["fields"].as_object() {
+                record.fields.extend(fields.clone());
+            }
+            Json(record.to_json()).into_response()
+        }
+        (Method::DELETE, Some(record_id)) => {
+            records.retain(|record| record.id != record_id);
+            Json(serde_json::json!({"id": record_id, "deleted": true})).into_response()
+        }
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn query_parameter(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| {
+            percent_encoding::percent_decode_str(&value.replace('+', " "))
+                .decode_utf8_lossy()
+                .to_string()
+        })
+    })
+}
+
+fn list_records(records: &[MockRecord], table: &str, formula: &str) -> Response {
+    let records = records
+        .iter()
+        .filter(|record| record.table == table && matches_formula(record, formula))
+        .map(MockRecord::to_json)
+        .collect::<Vec<_>>();
+    Json(serde_json::json!({ "records": records })).into_response()
+}
+
+/// Evaluates the `{field}='value'` comparisons of the formula, case-insensitively: a record
+/// matches when, for every compared field, its value is one of the compared values. `NOT({field}
+/// ='')` requires the field to be set, and the other functions are ignored.
+fn matches_formula(record: &MockRecord, formula: &str) -> bool {
+    let comparison =
+        regex::Regex::new(r"(NOT\()?(?:LOWER\()?\{([^}]+)\}\)?\s*=\s*'((?:[^'\\]|\\.)*)'").unwrap();
+    let mut allowed_values = std::collections::HashMap::<String, Vec<String>>::new();
+    for captures in comparison.captures_iter(formula) {
+        let field = captures[2].to_string();
+        let value = record.fields.get(&field).and_then(|value| value.as_str());
+        if captures.get(1).is_some() {
+            if value
+                .unwrap_or_default()
+                .eq_ignore_ascii_case(&captures[3].replace("\\'", "'"))
+            {
+                return false;
+            }
+            continue;
+        }
+        allowed_values
+            .entry(field)
+            .or_default()
+            .push(captures[3].replace("\\'", "'"));
+    }
+    allowed_values.iter().all(|(field, values)| {
+        let value = record
+            .fields
+            .get(field)
+            .and_then(|value| value.as_str())
+            .unwrap_or_default();
+        values
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(value))
+    })
+}
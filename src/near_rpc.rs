@@ -0,0 +1,89 @@
+use crate::{AppState, KycError};
+
+/// How long a NEAR RPC call may take before the verification fails, so a slow RPC node does not
+/// hold the requests waiting for it.
+const NEAR_RPC_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// HTTP client of the `NEAR_RPC_URL` calls, shared so the connections are pooled across requests.
+/// It is built by the first call, as most deployments never verify transactions or access keys.
+#[derive(Default)]
+pub(crate) struct NearRpcClient {
+    client: tokio::sync::OnceCell<reqwest::Client>,
+}
+
+impl NearRpcClient {
+    async fn client(&self) -> Result<&reqwest::Client, KycError> {
+        self.client
+            .get_or_try_init(|| async {
+                reqwest::Client::builder().timeout(NEAR_RPC_TIMEOUT).build()
+            })
+            .await
+            .map_err(|err| {
+                tracing::warn!("failed to build the NEAR RPC HTTP client: {err}");
+                KycError::DependencyUnavailable("near_rpc")
+            })
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<serde_json::Value>,
+}
+
+/// Calls a NEAR JSON-RPC method, returning `None` when the RPC reports an error for the
+/// request (e.g. an unknown transaction or access key).
+pub(crate) async fn call<T: serde::de::DeserializeOwned>(
+    state: &AppState,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<Option<T>, KycError> {
+    let response: RpcResponse<T> = state
+        .near_rpc_client
+        .client()
+        .await?
+        .post(&state.config.near_rpc_url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "kyc-proxy",
+            "method": method,
+            "params": params,
+        }))
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|_| KycError::NearRpcError)?
+        .json()
+        .await
+        .map_err(|_| KycError::NearRpcError)?;
+    match response {
+        RpcResponse {
+            result: Some(result),
+            ..
+        } => Ok(Some(result)),
+        RpcResponse { error: Some(_), .. } => Ok(None),
+        RpcResponse { .. } => Err(KycError::NearRpcError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_airtable::MockAirtable;
+
+    #[tokio::test]
+    async fn client_is_built_by_the_first_call() {
+        let airtable = MockAirtable::start().await;
+        let state = AppState::builder()
+            .with_config(crate::config::Config {
+                near_rpc_url: format!("{}/rpc", airtable.base_url()),
+                ..airtable.config()
+            })
+            .build();
+        assert!(state.near_rpc_client.client.get().is_none());
+
+        let _ = call::<serde_json::Value>(&state, "status", serde_json::json!([])).await;
+        assert!(state.near_rpc_client.client.get().is_some());
+        assert_eq!(airtable.requests()[0].path, "/rpc");
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::AppState;
+
+/// How often the expired nonces are pruned from the file while no nonces are issued or used.
+const NONCE_PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Restores the ownership nonces saved by the previous instance, if any, so the proofs in
+/// progress survive a restart.
+pub(crate) fn load_ownership_nonces(state: &AppState, path: &Path) {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return,
+        Err(err) => {
+            tracing::warn!(path = %path.display(), "failed to read the persisted ownership nonces: {err}");
+            return;
+        }
+    };
+    match state
+        .ownership_nonces
+        .load_json(&json, state.field_encryptor.as_ref())
+    {
+        Ok(loaded) => {
+            tracing::info!(path = %path.display(), loaded, "Persisted ownership nonces were loaded");
+        }
+        Err(err) => {
+            tracing::warn!(path = %path.display(), "failed to parse the persisted ownership nonces: {err}");
+        }
+    }
+}
+
+/// Saves the ownership nonces whenever they are issued or used, so a used nonce cannot be used
+/// again after a restart, and prunes the expired ones.
+pub(crate) async fn run_ownership_nonce_persistence(state: Arc<AppState>, path: PathBuf) {
+    loop {
+        let changed = tokio::time::timeout(NONCE_PRUNE_INTERVAL, state.ownership_nonces.changed())
+            .await
+            .is_ok();
+        if !changed && state.ownership_nonces.prune() == 0 {
+            continue;
+        }
+        if let Err(err) = save_ownership_nonces(&state, &path).await {
+            tracing::warn!(path = %path.display(), "failed to persist the ownership nonces: {err}");
+        }
+    }
+}
+
+async fn save_ownership_nonces(state: &AppState, path: &Path) -> std::io::Result<()> {
+    crate::cache_persist::write_file_atomically(
+        path,
+        state
+            .ownership_nonces
+            .to_json(state.field_encryptor.as_ref()),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn nonces_survive_a_restart_and_stay_single_use() {
+        let path = std::env::temp_dir().join(format!("nonces_{}.json", uuid::Uuid::new_v4()));
+        let alice: near_account_id::AccountId = "alice.near".parse().unwrap();
+        let config = || Config {
+            field_encryption_key: Some([7; 32]),
+            ..Default::default()
+        };
+
+        let state = AppState::builder().with_config(config()).build();
+        let nonce = state.ownership_nonces.issue(alice.clone());
+        save_ownership_nonces(&state, &path).await.unwrap();
+
+        let restarted_state = AppState::builder().with_config(config()).build();
+        load_ownership_nonces(&restarted_state, &path);
+        assert!(restarted_state.ownership_nonces.consume(&alice, &nonce));
+        save_ownership_nonces(&restarted_state, &path)
+            .await
+            .unwrap();
+
+        // The nonce was used before the second restart
+        let restarted_state = AppState::builder().with_config(config()).build();
+        load_ownership_nonces(&restarted_state, &path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(!restarted_state.ownership_nonces.consume(&alice, &nonce));
+    }
+
+    #[tokio::test]
+    async fn nonces_are_saved_when_they_change() {
+        let path = std::env::temp_dir().join(format!("nonces_{}.json", uuid::Uuid::new_v4()));
+        let state = AppState::builder().build();
+        tokio::spawn(run_ownership_nonce_persistence(state.clone(), path.clone()));
+
+        let nonce = state.ownership_nonces.issue("alice.near".parse().unwrap());
+        let mut saved = String::new();
+        for _ in 0..100 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            saved = std::fs::read_to_string(&path).unwrap_or_default();
+            if saved.contains(&nonce) {
+                break;
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(saved.contains(&nonce));
+    }
+
+    #[test]
+    fn missing_file_has_no_nonces() {
+        let state = AppState::builder().build();
+        load_ownership_nonces(&state, Path::new("/nonexistent/nonces.json"));
+        assert_eq!(state.ownership_nonces.to_json(None), b"[]");
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use near_account_id::AccountId;
+use ring::{rand::SecureRandom, signature};
+
+use crate::{
+    encryption::{EncryptedBytes, FieldEncryptor},
+    near_rpc, wallet, AppState, KycError,
+};
+
+/// How long an issued nonce can be used to prove the account ownership.
+pub(crate) const NONCE_TTL: Duration = Duration::from_secs(5 * 60);
+/// Unused nonces kept per account; issuing more revokes the oldest ones.
+const MAX_NONCES_PER_ACCOUNT: usize = 5;
+
+/// An issued nonce as written to the `OWNERSHIP_NONCE_PERSIST_PATH`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PersistedNonce {
+    #[serde(flatten)]
+    nonce: PersistedNonceAccount,
+    issued_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The nonce and its account, which prove the ownership of the account to whoever reads them, so
+/// they are encrypted when a `FIELD_ENCRYPTION_KEY` is set.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum PersistedNonceAccount {
+    Plain {
+        nonce: String,
+        account_id: AccountId,
+    },
+    Encrypted {
+        /// `[nonce, account_id]`
+        encrypted: EncryptedBytes,
+    },
+}
+
+/// Nonces issued for ownership proofs, each of which can be used once.
+#[derive(Default)]
+pub(crate) struct Nonces {
+    nonces: Mutex<HashMap<String, (AccountId, Instant)>>,
+    /// Notified whenever nonces are issued or removed, so they can be saved right away.
+    changed: tokio::sync::Notify,
+}
+
+impl Nonces {
+    /// Issues a random nonce for the account, returning it base64-encoded.
+    pub(crate) fn issue(&self, account_id: AccountId) -> String {
+        let mut bytes = [0; 32];
+        ring::rand::SystemRandom::new()
+            .fill(&mut bytes)
+            .expect("the system random number generator is available");
+        let nonce = base64::engine::general_purpose::STANDARD.encode(bytes);
+
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces.retain(|_, (_, issued_at)| issued_at.elapsed() < NONCE_TTL);
+        let mut account_nonces = nonces
+            .iter()
+            .filter(|(_, (nonce_account_id, _))| nonce_account_id == &account_id)
+            .map(|(nonce, (_, issued_at))| (*issued_at, nonce.clone()))
+            .collect::<Vec<_>>();
+        if account_nonces.len() >= MAX_NONCES_PER_ACCOUNT {
+            account_nonces.sort_unstable();
+            let revoked = account_nonces.len() + 1 - MAX_NONCES_PER_ACCOUNT;
+            for (_, nonce) in &account_nonces[..revoked] {
+                nonces.remove(nonce);
+            }
+        }
+        nonces.insert(nonce.clone(), (account_id, Instant::now()));
+        self.changed.notify_one();
+        nonce
+    }
+
+    /// Revokes the nonces issued for the account.
+    pub(crate) fn evict(&self, account_id: &AccountId) {
+        self.nonces
+            .lock()
+            .unwrap()
+            .retain(|_, (nonce_account_id, _)| nonce_account_id != account_id);
+        self.changed.notify_one();
+    }
+
+    /// Consumes the nonce, returning whether it was issued for the account and has not expired.
+    pub(crate) fn consume(&self, account_id: &AccountId, nonce: &str) -> bool {
+        let Some((nonce_account_id, issued_at)) = self.nonces.lock().unwrap().remove(nonce) else {
+            return false;
+        };
+        self.changed.notify_one();
+        &nonce_account_id == account_id && issued_at.elapsed() < NONCE_TTL
+    }
+
+    /// Removes the expired nonces, returning how many were removed.
+    pub(crate) fn prune(&self) -> usize {
+        let mut nonces = self.nonces.lock().unwrap();
+        let issued = nonces.len();
+        nonces.retain(|_, (_, issued_at)| issued_at.elapsed() < NONCE_TTL);
+        issued - nonces.len()
+    }
+
+    /// Waits until nonces are issued or removed, returning right away if they were since the last
+    /// call.
+    pub(crate) async fn changed(&self) {
+        self.changed.notified().await;
+    }
+
+    /// Serializes the nonces which have not expired yet.
+    pub(crate) fn to_json(&self, encryptor: Option<&FieldEncryptor>) -> Vec<u8> {
+        let now = chrono::Utc::now();
+        let nonces = self
+            .nonces
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (_, issued_at))| issued_at.elapsed() < NONCE_TTL)
+            .map(|(nonce, (account_id, issued_at))| PersistedNonce {
+                nonce: match encryptor {
+                    Some(encryptor) => PersistedNonceAccount::Encrypted {
+                        encrypted: encryptor.encrypt(&(nonce, account_id)),
+                    },
+                    None => PersistedNonceAccount::Plain {
+                        nonce: nonce.clone(),
+                        account_id: account_id.clone(),
+                    },
+                },
+                issued_at: now - issued_at.elapsed(),
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_vec(&nonces).expect("the nonces are serializable")
+    }
+
+    /// Adds the nonces serialized with [`Nonces::to_json`], skipping the ones which expired in the
+    /// meantime and the encrypted ones which cannot be decrypted with the current key. Returns how
+    /// many were added.
+    pub(crate) fn load_json(
+        &self,
+        json: &[u8],
+        encryptor: Option<&FieldEncryptor>,
+    ) -> Result<usize, serde_json::Error> {
+        let persisted_nonces: Vec<PersistedNonce> = serde_json::from_slice(json)?;
+        let now = chrono::Utc::now();
+        let mut nonces = self.nonces.lock().unwrap();
+        let mut loaded = 0;
+        for persisted_nonce in persisted_nonces {
+            let Some(issued_at) = (now - persisted_nonce.issued_at)
+                .to_std()
+                .ok()
+                .filter(|age| *age < NONCE_TTL)
+                .and_then(|age| Instant::now().checked_sub(age))
+            else {
+                continue;
+            };
+            let (nonce, account_id) = match persisted_nonce.nonce {
+                PersistedNonceAccount::Plain { nonce, account_id } => (nonce, account_id),
+                PersistedNonceAccount::Encrypted { encrypted } => {
+                    match encryptor.map(|encryptor| encryptor.decrypt(&encrypted)) {
+                        Some(Ok(nonce)) => nonce,
+                        _ => continue,
+                    }
+                }
+            };
+            nonces.insert(nonce, (account_id, issued_at));
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub(crate) struct OwnershipProof {
+    pub account_id: AccountId,
+    pub nonce: String,
+    /// Signature of the nonce, either base64-encoded or `ed25519:` followed by base58.
+    pub signature: String,
+    /// `ed25519:` followed by the base58-encoded key.
+    pub public_key: String,
+}
+
+/// Checks the ed25519 signature of the nonce, which is signed as the UTF-8 bytes of its
+/// base64-encoded form.
+pub(crate) fn verify_signature(proof: &OwnershipProof) -> bool {
+    let Some(public_key) = proof
+        .public_key
+        .strip_prefix("ed25519:")
+        .and_then(wallet::decode_base58)
+    else {
+        return false;
+    };
+    let signature = match proof.signature.strip_prefix("ed25519:") {
+        Some(signature) => wallet::decode_base58(signature),
+        None => base64::engine::general_purpose::STANDARD
+            .decode(&proof.signature)
+            .ok(),
+    };
+    let Some(signature) = signature else {
+        return false;
+    };
+    signature::UnparsedPublicKey::new(&signature::ED25519, public_key)
+        .verify(proof.nonce.as_bytes(), &signature)
+        .is_ok()
+}
+
+/// Whether the public key is an access key of the account, according to the NEAR RPC.
+pub(crate) async fn is_access_key(
+    state: &AppState,
+    account_id: &AccountId,
+    public_key: &str,
+) -> Result<bool, KycError> {
+    let access_key: Option<serde_json::Value> = near_rpc::call(
+        state,
+        "query",
+        serde_json::json!({
+            "request_type": "view_access_key",
+            "finality": "final",
+            "account_id": account_id,
+            "public_key": public_key,
+        }),
+    )
+    .await?;
+    // Older RPC nodes report unknown keys as a result with an `error` field
+    Ok(access_key.is_some_and(|access_key| access_key.get("permission").is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alice() -> AccountId {
+        "alice.near".parse().unwrap()
+    }
+
+    #[test]
+    fn nonce_can_only_be_used_once_by_its_account() {
+        let nonces = Nonces::default();
+        let nonce = nonces.issue(alice());
+        assert!(!nonces.consume(&"bob.near".parse().unwrap(), &nonce));
+        // The failed attempt used the nonce up
+        assert!(!nonces.consume(&alice(), &nonce));
+
+        let nonce = nonces.issue(alice());
+        assert!(nonces.consume(&alice(), &nonce));
+        assert!(!nonces.consume(&alice(), &nonce));
+    }
+
+    #[test]
+    fn oldest_nonces_of_the_account_are_revoked() {
+        let nonces = Nonces::default();
+        let issued = (0..MAX_NONCES_PER_ACCOUNT + 2)
+            .map(|_| {
+                let nonce = nonces.issue(alice());
+                std::thread::sleep(Duration::from_millis(1));
+                nonce
+            })
+            .collect::<Vec<_>>();
+        let bob_nonce = nonces.issue("bob.near".parse().unwrap());
+
+        assert_eq!(
+            nonces.nonces.lock().unwrap().len(),
+            MAX_NONCES_PER_ACCOUNT + 1
+        );
+        assert!(!nonces.consume(&alice(), &issued[0]));
+        assert!(!nonces.consume(&alice(), &issued[1]));
+        assert!(issued[2..]
+            .iter()
+            .all(|nonce| nonces.consume(&alice(), nonce)));
+        assert!(nonces.consume(&"bob.near".parse().unwrap(), &bob_nonce));
+    }
+
+    #[test]
+    fn expired_nonces_are_pruned() {
+        let nonces = Nonces::default();
+        let nonce = nonces.issue(alice());
+        nonces
+            .nonces
+            .lock()
+            .unwrap()
+            .insert("expired".to_string(), (alice(), Instant::now() - NONCE_TTL));
+
+        assert_eq!(nonces.prune(), 1);
+        assert_eq!(nonces.prune(), 0);
+        assert!(nonces.consume(&alice(), &nonce));
+    }
+
+    #[test]
+    fn nonces_are_encrypted_with_the_field_encryption_key() {
+        let encryptor = FieldEncryptor::new(&[7; 32]);
+        let nonces = Nonces::default();
+        let nonce = nonces.issue(alice());
+        let json = nonces.to_json(Some(&encryptor));
+        let json_text = String::from_utf8(json.clone()).unwrap();
+        assert!(!json_text.contains(&nonce));
+        assert!(!json_text.contains("alice.near"));
+
+        let other_key_nonces = Nonces::default();
+        assert_eq!(
+            other_key_nonces
+                .load_json(&json, Some(&FieldEncryptor::new(&[8; 32])))
+                .unwrap(),
+            0
+        );
+        let loaded_nonces = Nonces::default();
+        assert_eq!(loaded_nonces.load_json(&json, Some(&encryptor)).unwrap(), 1);
+        assert!(loaded_nonces.consume(&alice(), &nonce));
+    }
+
+    #[test]
+    fn expired_nonces_are_not_loaded() {
+        let issued_at = chrono::Utc::now() - chrono::TimeDelta::minutes(6);
+        let json = serde_json::json!([{
+            "nonce": "expired",
+            "account_id": "alice.near",
+            "issued_at": issued_at,
+        }]);
+        let nonces = Nonces::default();
+        assert_eq!(
+            nonces.load_json(json.to_string().as_bytes(), None).unwrap(),
+            0
+        );
+        assert!(!nonces.consume(&alice(), "expired"));
+    }
+}
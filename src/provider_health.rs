@@ -0,0 +1,76 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+use crate::{fetch_airtable_records, select_kyc_status, AirtableResponse, AppState};
+
+/// Consecutive failed probes after which the provider is reported as degraded.
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// Outcome of the recent provider health check probes.
+#[derive(Default)]
+pub(crate) struct ProviderHealth {
+    consecutive_failures: AtomicU32,
+    degraded: AtomicBool,
+}
+
+impl ProviderHealth {
+    /// Records a probe outcome and returns whether the provider just became degraded.
+    pub(crate) fn record(&self, success: bool) -> bool {
+        if success {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+            self.degraded.store(false, Ordering::Relaxed);
+            return false;
+        }
+        let consecutive_failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        consecutive_failures >= FAILURE_THRESHOLD && !self.degraded.swap(true, Ordering::Relaxed)
+    }
+
+    pub(crate) fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+}
+
+/// Periodically looks up the `HEALTH_CHECK_ACCOUNT_ID` probe account, bypassing the caches, and
+/// checks it still has the expected status. This catches Airtable responses which succeed but
+/// are wrong (e.g. no records after an API change), which the circuit breaker does not notice.
+pub(crate) async fn run_provider_health_check(
+    state: Arc<AppState>,
+    account_id: near_account_id::AccountId,
+) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        state.config.health_check_interval_secs,
+    ));
+    loop {
+        interval.tick().await;
+
+        // The probes run in the background, so their failures should not block the lookups
+        let kyc_status =
+            crate::circuit_breaker::in_batch_circuit(fetch_airtable_records::<AirtableResponse>(
+                &state,
+                &account_id,
+                &state.config.airtable_view_name,
+            ))
+            .await
+            .map(|body| select_kyc_status(&body.records, state.config.tie_breaking_policy).0);
+        let expected_status = state.config.health_check_expected_status;
+        let success = match kyc_status {
+            Ok(kyc_status) if kyc_status == expected_status => true,
+            Ok(kyc_status) => {
+                tracing::warn!(%account_id, ?expected_status, ?kyc_status, "Provider health check probe returned an unexpected status");
+                false
+            }
+            Err(err) => {
+                tracing::warn!(%account_id, error = err.status_and_message().1, "Provider health check probe failed");
+                false
+            }
+        };
+        if state.provider_health.record(success) {
+            tracing::error!(
+                %account_id,
+                "Provider health check failed {FAILURE_THRESHOLD} times in a row, the provider is degraded"
+            );
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Share of the quota after which a warning is reported.
+const WARNING_THRESHOLD: f64 = 0.8;
+
+/// Counts the Airtable API calls made since startup (or the last reset) against the monthly
+/// quota of the Airtable plan.
+pub(crate) struct AirtableQuota {
+    monthly_quota: u64,
+    calls: AtomicU64,
+}
+
+impl AirtableQuota {
+    pub(crate) fn new(monthly_quota: u64) -> Self {
+        Self {
+            monthly_quota,
+            calls: AtomicU64::new(0),
+        }
+    }
+
+    fn warning_threshold(&self) -> u64 {
+        (self.monthly_quota as f64 * WARNING_THRESHOLD) as u64
+    }
+
+    /// Counts a call, logging a warning when the call reaches the warning threshold, and returns
+    /// whether the quota is used up.
+    pub(crate) fn record_call(&self) -> bool {
+        let calls = self.calls.fetch_add(1, Ordering::Relaxed).saturating_add(1);
+        if calls == self.warning_threshold() {
+            tracing::warn!(
+                calls,
+                monthly_quota = self.monthly_quota,
+                "Airtable API calls reached {}% of the monthly quota",
+                WARNING_THRESHOLD * 100.0
+            );
+        }
+        calls >= self.monthly_quota
+    }
+
+    pub(crate) fn is_warning(&self) -> bool {
+        self.calls.load(Ordering::Relaxed) >= self.warning_threshold()
+    }
+
+    pub(crate) fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+    }
+}
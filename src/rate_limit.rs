@@ -0,0 +1,45 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Allows at most `limit` hits per key within a sliding time window.
+pub(crate) struct RateLimiter<K> {
+    limit: usize,
+    window: Duration,
+    hits: Mutex<HashMap<K, VecDeque<Instant>>>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    pub(crate) fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: Default::default(),
+        }
+    }
+
+    /// Records a hit for the key, unless the key has already reached the limit.
+    pub(crate) fn try_acquire(&self, key: K) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+        hits.retain(|_, key_hits| {
+            while key_hits
+                .front()
+                .is_some_and(|hit| now.duration_since(*hit) >= self.window)
+            {
+                key_hits.pop_front();
+            }
+            !key_hits.is_empty()
+        });
+
+        let key_hits = hits.entry(key).or_default();
+        if key_hits.len() >= self.limit {
+            return false;
+        }
+        key_hits.push_back(now);
+        true
+    }
+}
@@ -0,0 +1,105 @@
+use axum::http::{header, HeaderMap, HeaderName};
+
+/// Headers carrying credentials, whose values never make it into the logs.
+const SENSITIVE_HEADERS: [HeaderName; 6] = [
+    header::AUTHORIZATION,
+    HeaderName::from_static("x-api-key"),
+    HeaderName::from_static("x-admin-key"),
+    HeaderName::from_static("x-admin-signature"),
+    HeaderName::from_static("x-webhook-token"),
+    HeaderName::from_static("x-registration-token"),
+];
+
+/// Formats the headers for logging, with the values of the [`SENSITIVE_HEADERS`] redacted.
+/// Headers are only ever logged through it.
+pub(crate) struct RedactedHeaders<'a>(pub &'a HeaderMap);
+
+impl std::fmt::Debug for RedactedHeaders<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(name, value)| {
+                let value = if name == header::AUTHORIZATION {
+                    "Bearer [REDACTED]"
+                } else if SENSITIVE_HEADERS.contains(name) {
+                    "[REDACTED]"
+                } else {
+                    value.to_str().unwrap_or("[NON-ASCII]")
+                };
+                (name.as_str(), value)
+            }))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{body::Body, extract::Request};
+
+    use super::*;
+    use crate::mock_airtable::{self, MockAirtable};
+
+    const API_KEY: &str = "kyc-proxy-api-key-0123456789";
+
+    /// Log output written by the subscriber.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sensitive_header_values_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer token".parse().unwrap());
+        headers.insert("x-api-key", API_KEY.parse().unwrap());
+        headers.insert(header::ACCEPT, "application/json".parse().unwrap());
+
+        let formatted = format!("{:?}", RedactedHeaders(&headers));
+        assert_eq!(
+            formatted,
+            r#"{"authorization": "Bearer [REDACTED]", "x-api-key": "[REDACTED]", "accept": "application/json"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn api_keys_never_reach_the_logs() {
+        let logs = CapturedLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = airtable.router_with(airtable.secret_store().with("API_KEYS", API_KEY));
+        let (status, _) = mock_airtable::send(
+            &router,
+            Request::get("/v1/kyc/alice.near")
+                .header("X-API-Key", API_KEY)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("Request received"));
+        assert!(logs.contains(r#""x-api-key": "[REDACTED]""#));
+        assert!(!logs.contains(API_KEY));
+    }
+}
@@ -0,0 +1,109 @@
+use ring::hmac;
+
+use crate::signing::to_hex;
+
+/// KYC portal the registration links point to, and the secret their tokens are signed with.
+pub(crate) struct RegistrationLinkConfig {
+    pub portal_url: reqwest::Url,
+    pub secret: String,
+}
+
+/// Computes the hex-encoded `HMAC-SHA256(secret, account_id + expires)` token of a link, where
+/// `expires` is the Unix time in seconds until which the link is valid.
+pub(crate) fn sign_token(
+    secret: &str,
+    account_id: &near_account_id::AccountId,
+    expires: i64,
+) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    to_hex(hmac::sign(&key, format!("{account_id}{expires}").as_bytes()).as_ref())
+}
+
+/// Builds the portal URL pre-filled with the account, e.g.
+/// `https://kyc-portal.example.com/start?near_account=alice.near&token=...&expires=...`, so the
+/// portal knows which account is submitting.
+pub(crate) fn registration_link(
+    config: &RegistrationLinkConfig,
+    account_id: &near_account_id::AccountId,
+    expires: i64,
+) -> reqwest::Url {
+    let mut link = config.portal_url.clone();
+    link.query_pairs_mut()
+        .append_pair("near_account", account_id.as_str())
+        .append_pair("token", &sign_token(&config.secret, account_id, expires))
+        .append_pair("expires", &expires.to_string());
+    link
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "portal-secret";
+
+    /// Checks the token the way the portal does.
+    fn verify_token(account_id: &str, expires: &str, token: &str) -> bool {
+        let Some(tag) = (0..token.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(token.get(index..index + 2)?, 16).ok())
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET.as_bytes());
+        hmac::verify(&key, format!("{account_id}{expires}").as_bytes(), &tag).is_ok()
+    }
+
+    fn link_parameters(link: &reqwest::Url) -> (String, String, String) {
+        let parameter = |name: &str| {
+            link.query_pairs()
+                .find_map(|(key, value)| (key == name).then(|| value.to_string()))
+                .unwrap()
+        };
+        (
+            parameter("near_account"),
+            parameter("expires"),
+            parameter("token"),
+        )
+    }
+
+    #[test]
+    fn links_embed_a_valid_token() {
+        let config = RegistrationLinkConfig {
+            portal_url: "https://kyc-portal.example.com/start".parse().unwrap(),
+            secret: SECRET.to_string(),
+        };
+        let link = registration_link(&config, &"alice.near".parse().unwrap(), 1_700_000_000);
+        assert!(link
+            .as_str()
+            .starts_with("https://kyc-portal.example.com/start?near_account=alice.near&token="));
+
+        let (account_id, expires, token) = link_parameters(&link);
+        assert_eq!(expires, "1700000000");
+        assert_eq!(token.len(), 64);
+        assert!(verify_token(&account_id, &expires, &token));
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let token = sign_token(SECRET, &"alice.near".parse().unwrap(), 1_700_000_000);
+        assert!(verify_token("alice.near", "1700000000", &token));
+
+        assert!(!verify_token("bob.near", "1700000000", &token));
+        assert!(!verify_token("alice.near", "1800000000", &token));
+        let flipped = if token.starts_with('0') { "1" } else { "0" };
+        assert!(!verify_token(
+            "alice.near",
+            "1700000000",
+            &format!("{flipped}{}", &token[1..])
+        ));
+        assert_ne!(
+            sign_token(
+                "other-secret",
+                &"alice.near".parse().unwrap(),
+                1_700_000_000
+            ),
+            token
+        );
+    }
+}
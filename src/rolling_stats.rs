@@ -0,0 +1,129 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const MAX_SAMPLES: usize = 1000;
+
+struct Sample {
+    finished_at: Instant,
+    duration: Duration,
+    success: bool,
+}
+
+/// Keeps the outcomes of the last [`MAX_SAMPLES`] Airtable calls.
+#[derive(Default)]
+pub(crate) struct RollingStats {
+    samples: Mutex<VecDeque<Sample>>,
+}
+
+#[derive(serde::Serialize)]
+pub(crate) struct RollingStatsSummary {
+    pub success_rate: Option<f64>,
+    pub p95_ms: Option<u128>,
+    pub p99_ms: Option<u128>,
+    pub error_count: usize,
+}
+
+impl RollingStats {
+    pub(crate) fn record(&self, duration: Duration, success: bool) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(Sample {
+            finished_at: Instant::now(),
+            duration,
+            success,
+        });
+    }
+
+    /// Summarizes the calls which finished within the `window`.
+    pub(crate) fn summary(&self, window: Duration) -> RollingStatsSummary {
+        let samples = self.samples.lock().unwrap();
+        let recent_samples = samples
+            .iter()
+            .filter(|sample| sample.finished_at.elapsed() <= window);
+
+        let mut durations = Vec::new();
+        let mut error_count = 0;
+        for sample in recent_samples {
+            durations.push(sample.duration);
+            if !sample.success {
+                error_count += 1;
+            }
+        }
+        durations.sort_unstable();
+
+        RollingStatsSummary {
+            success_rate: (!durations.is_empty())
+                .then(|| (durations.len() - error_count) as f64 / durations.len() as f64),
+            p95_ms: percentile(&durations, 0.95).map(|duration| duration.as_millis()),
+            p99_ms: percentile(&durations, 0.99).map(|duration| duration.as_millis()),
+            error_count,
+        }
+    }
+}
+
+/// Nearest-rank percentile of the sorted durations.
+fn percentile(sorted_durations: &[Duration], percentile: f64) -> Option<Duration> {
+    let rank = (percentile * sorted_durations.len() as f64).ceil() as usize;
+    sorted_durations.get(rank.saturating_sub(1)).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(durations: &[u64]) -> Vec<Duration> {
+        durations
+            .iter()
+            .copied()
+            .map(Duration::from_millis)
+            .collect()
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let durations = millis(&(1..=100).collect::<Vec<_>>());
+        assert_eq!(
+            percentile(&durations, 0.95),
+            Some(Duration::from_millis(95))
+        );
+        assert_eq!(
+            percentile(&durations, 0.99),
+            Some(Duration::from_millis(99))
+        );
+        assert_eq!(
+            percentile(&durations, 1.0),
+            Some(Duration::from_millis(100))
+        );
+
+        let durations = millis(&[10, 20, 30]);
+        assert_eq!(percentile(&durations, 0.5), Some(Duration::from_millis(20)));
+        assert_eq!(
+            percentile(&durations, 0.95),
+            Some(Duration::from_millis(30))
+        );
+        assert_eq!(percentile(&durations, 0.0), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn percentile_of_no_durations_is_none() {
+        assert_eq!(percentile(&[], 0.95), None);
+    }
+
+    #[test]
+    fn summary_counts_errors() {
+        let stats = RollingStats::default();
+        assert_eq!(stats.summary(Duration::from_secs(60)).success_rate, None);
+        for (duration, success) in [(10, true), (20, false), (30, true), (40, true)] {
+            stats.record(Duration::from_millis(duration), success);
+        }
+        let summary = stats.summary(Duration::from_secs(60));
+        assert_eq!(summary.success_rate, Some(0.75));
+        assert_eq!(summary.error_count, 1);
+        assert_eq!(summary.p95_ms, Some(40));
+    }
+}
@@ -0,0 +1,60 @@
+//! Admin endpoints, all of which require a request signed with the admin secret. Some of them
+//! predate the `/admin` prefix and are kept under `/v1` for existing clients.
+
+use std::sync::Arc;
+
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
+
+use crate::AppState;
+
+pub(super) fn router(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let v1_router = Router::new()
+        .route("/kyc/expired", get(crate::get_expired_kyc_accounts))
+        .route(
+            "/kyc/near/:parent_account/members",
+            get(crate::get_sub_account_members),
+        )
+        .route("/kyc/summary", get(crate::get_kyc_summary))
+        .route("/kyc/random-sample", get(crate::get_random_kyc_sample))
+        .route("/kyc/leaderboard", get(crate::get_kyc_leaderboard))
+        .route("/kyc/:account_id", delete(crate::erase_account_kyc_data))
+        .route(
+            "/kyc/:account_id/refresh",
+            post(crate::refresh_account_kyc_status),
+        )
+        .route(
+            "/kyc/:account_id/timeline",
+            get(crate::get_account_kyc_timeline),
+        )
+        .route(
+            "/kyc/:account_id/compliance-report",
+            get(crate::get_account_compliance_report),
+        );
+    let mut admin_router = Router::new()
+        .route("/kyc/:account_id", get(crate::get_admin_account_kyc_status))
+        .route("/config/cors", post(crate::update_cors_config))
+        .route("/quota-reset", post(crate::reset_airtable_quota))
+        .route("/rotate-key", post(crate::rotate_airtable_api_key))
+        .route("/airtable/schema", get(crate::get_airtable_schema))
+        .route("/audit/:account_id", get(crate::get_account_audit_events))
+        .route("/deletion-log", get(crate::get_deletion_log));
+    if state.config.environment != crate::config::Environment::Production {
+        admin_router = admin_router.route(
+            "/simulate/status-change",
+            post(crate::simulate_status_change),
+        );
+    }
+
+    Router::new()
+        .nest("/v1", v1_router)
+        .nest("/admin", admin_router)
+        .route_layer(axum::middleware::from_fn(super::require_json_content_type))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state,
+            crate::signing::require_admin_signature,
+        ))
+        .route_layer(axum::middleware::from_fn(super::no_store))
+}
@@ -0,0 +1,52 @@
+//! KYC lookup endpoints, nested under `/v1`, which require one of the `API_KEYS` when configured.
+//! Registration also checks its own token in the handler.
+
+use std::sync::Arc;
+
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use crate::AppState;
+
+pub(super) fn router(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let batch_router = Router::new()
+        .route("/kyc/batch", post(crate::get_batch_kyc_status))
+        .route("/kyc/batch/diff", post(crate::get_batch_kyc_status_diff))
+        .route("/kyc/batch/async", post(crate::create_batch_job))
+        .route("/kyc/batch/job/:job_id", get(crate::get_batch_job))
+        .route_layer(axum::middleware::from_fn(super::no_store));
+
+    Router::new()
+        .route("/kyc/:account_id", get(crate::get_account_kyc_status))
+        .route("/kyc/:account_id/badge", get(crate::get_account_kyc_badge))
+        .route(
+            "/kyc/:account_id/exists",
+            get(crate::check_account_kyc_status_exists),
+        )
+        .route("/kyc/register", post(crate::register_account))
+        .route(
+            "/kyc/register-link/:account_id",
+            get(crate::get_registration_link),
+        )
+        .merge(batch_router)
+        .route(
+            "/kyc/check/:account_id",
+            get(crate::check_account_kyc_status),
+        )
+        .route("/kyc/nonce/:account_id", get(crate::get_ownership_nonce))
+        .route(
+            "/kyc/verify-ownership",
+            post(crate::verify_account_ownership),
+        )
+        .route(
+            "/kyc/verify-batch-ownership",
+            post(crate::verify_batch_account_ownership),
+        )
+        .route_layer(axum::middleware::from_fn(super::require_json_content_type))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state,
+            super::require_api_key,
+        ))
+}
@@ -0,0 +1,371 @@
+//! Endpoint groups, each with its own authentication requirements.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+
+use crate::AppState;
+
+mod admin;
+mod api;
+mod public;
+
+pub(crate) fn router(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let max_body_bytes = state.config.max_request_body_bytes;
+    Router::new()
+        .merge(public::router(state.clone()))
+        .nest(
+            "/v1",
+            limit_request_body(api::router(state.clone()), max_body_bytes),
+        )
+        .merge(limit_request_body(
+            admin::router(state.clone()),
+            max_body_bytes,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            response_timeout,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            block_user_agents,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            simulate_chaos,
+        ))
+        .layer(axum::middleware::from_fn_with_state(state, log_requests))
+}
+
+/// Rejects bodies larger than `MAX_REQUEST_BODY_BYTES`, which replaces the default limit of the
+/// body extractors.
+fn limit_request_body(
+    router: Router<Arc<AppState>>,
+    max_body_bytes: usize,
+) -> Router<Arc<AppState>> {
+    router
+        .layer(axum::extract::DefaultBodyLimit::disable())
+        .layer(tower_http::limit::RequestBodyLimitLayer::new(
+            max_body_bytes,
+        ))
+        .layer(axum::middleware::map_response(request_too_large))
+}
+
+/// Gives the oversized body rejections, whether by the limit layer or by an extractor, the same
+/// JSON body as the other errors.
+async fn request_too_large(response: Response) -> Response {
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return response;
+    }
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(serde_json::json!({
+            "error_code": "REQUEST_TOO_LARGE",
+        })),
+    )
+        .into_response()
+}
+
+/// Delays the requests and fails some of them as configured with `SIMULATE_LATENCY_MS` and
+/// `SIMULATE_ERROR_RATE`, which are only allowed in development and test environments.
+async fn simulate_chaos(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    use ring::rand::SecureRandom;
+
+    let Some(chaos) = state.config.chaos else {
+        return next.run(request).await;
+    };
+    tokio::time::sleep(std::time::Duration::from_millis(chaos.latency_ms)).await;
+    let mut random = [0; 4];
+    ring::rand::SystemRandom::new()
+        .fill(&mut random)
+        .expect("the system random number generator is available");
+    if (u32::from_le_bytes(random) as f64 / u32::MAX as f64) < chaos.error_rate {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error_code": "SIMULATED_ERROR",
+            })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Logs the handled requests, sampled with `LOG_SAMPLE_RATE`. Server errors are always logged.
+async fn log_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    tracing::debug!(%method, %path, headers = ?crate::redact::RedactedHeaders(request.headers()), "Request received");
+    let started_at = std::time::Instant::now();
+    let response = next.run(request).await;
+
+    let status = response.status();
+    if status.is_server_error() {
+        tracing::warn!(%method, %path, status = status.as_u16(), duration_ms = started_at.elapsed().as_millis(), "Request failed");
+        return response;
+    }
+    // Lookups are sampled by account, so all or none of the requests for an account within a
+    // minute are logged
+    let sample_key = response
+        .extensions()
+        .get::<crate::audit::AuditedLookup>()
+        .map_or(path.as_str(), |lookup| lookup.account_id.as_str());
+    if is_sampled(
+        sample_key,
+        chrono::Utc::now().timestamp() / 60,
+        state.config.log_sample_rate,
+    ) {
+        tracing::info!(%method, %path, status = status.as_u16(), duration_ms = started_at.elapsed().as_millis(), "Request handled");
+    }
+    response
+}
+
+/// Deterministic sampling decision for the key within the given minute.
+fn is_sampled(key: &str, minute: i64, sample_rate: f64) -> bool {
+    use std::hash::{Hash, Hasher};
+
+    if sample_rate >= 1.0 {
+        return true;
+    }
+    let mut hasher = std::hash::DefaultHasher::new();
+    (key, minute).hash(&mut hasher);
+    (hasher.finish() as f64 / u64::MAX as f64) < sample_rate
+}
+
+/// Rejects requests from the `BLOCKED_USER_AGENTS`, such as known bot scanners.
+async fn block_user_agents(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(blocked_user_agents) = &state.config.blocked_user_agents {
+        let user_agent = request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok());
+        if user_agent.is_some_and(|user_agent| blocked_user_agents.is_match(user_agent)) {
+            state.metrics.increment_user_agent_blocked();
+            return StatusCode::FORBIDDEN.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Responds with 503 when the handler takes longer than `RESPONSE_TIMEOUT_MS`, so clients get a
+/// status code rather than hitting their own timeout.
+async fn response_timeout(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let timeout = std::time::Duration::from_millis(state.config.response_timeout_ms);
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "error_code": "SERVER_TIMEOUT",
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Keeps the responses out of any HTTP cache, for endpoints with per-requester or admin data.
+async fn no_store(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        axum::http::HeaderValue::from_static("no-store"),
+    );
+    response
+}
+
+/// Rejects requests without one of the `API_KEYS` in the `X-API-Key` header. Everyone is let
+/// through when no keys are configured.
+async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let api_keys = &state.config.api_keys;
+    if api_keys.is_empty() {
+        return next.run(request).await;
+    }
+    let provided = request
+        .headers()
+        .get("X-API-Key")
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+    let is_valid = api_keys.iter().any(|api_key| {
+        ring::constant_time::verify_slices_are_equal(api_key.as_bytes(), provided).is_ok()
+    });
+    if !is_valid {
+        return crate::KycError::Unauthorized.into_response();
+    }
+    next.run(request).await
+}
+
+/// Rejects requests with a non-JSON body up front, instead of the less helpful rejections of the
+/// `Json` extractor. Requests without a body (e.g. admin actions) need no content type.
+async fn require_json_content_type(request: Request, next: Next) -> Response {
+    let has_body = request.headers().contains_key(header::TRANSFER_ENCODING)
+        || request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .is_some_and(|content_length| content_length != "0");
+    let is_json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|mime_type| mime_type.trim().eq_ignore_ascii_case("application/json"));
+    if [Method::POST, Method::PUT, Method::PATCH].contains(request.method()) && has_body && !is_json
+    {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Json(serde_json::json!({
+                "error_code": "UNSUPPORTED_MEDIA_TYPE",
+                "message": "Content-Type must be application/json",
+            })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+
+    use super::*;
+    use crate::mock_airtable::{self, MockAirtable};
+
+    fn secured_router(airtable: &MockAirtable) -> Router {
+        airtable.router_with(
+            airtable
+                .secret_store()
+                .with("API_KEYS", "key-1, key-2")
+                .with("ADMIN_SECRET", "admin-secret"),
+        )
+    }
+
+    #[tokio::test]
+    async fn public_endpoints_need_no_credentials() {
+        let airtable = MockAirtable::start().await;
+        let (status, _) = mock_airtable::get(&secured_router(&airtable), "/livez").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn api_endpoints_require_an_api_key() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = secured_router(&airtable);
+        let lookup = |api_key: Option<&str>| {
+            let mut request = Request::get("/v1/kyc/alice.near");
+            if let Some(api_key) = api_key {
+                request = request.header("X-API-Key", api_key);
+            }
+            mock_airtable::send(&router, request.body(Body::empty()).unwrap())
+        };
+
+        assert_eq!(lookup(None).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(lookup(Some("key-3")).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(airtable.call_count(), 0);
+        let (status, body) = lookup(Some("key-2")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+    }
+
+    #[tokio::test]
+    async fn legacy_lookup_requires_an_api_key() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let router = secured_router(&airtable);
+
+        let (status, _) = mock_airtable::get(&router, "/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(airtable.call_count(), 0);
+        let (status, body) = mock_airtable::send(
+            &router,
+            Request::get("/kyc/alice.near")
+                .header("X-API-Key", "key-1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+    }
+
+    #[tokio::test]
+    async fn api_endpoints_are_open_without_api_keys() {
+        let airtable = MockAirtable::start().await;
+        let (status, _) = mock_airtable::get(&airtable.router(), "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn oversized_batch_bodies_are_rejected() {
+        let airtable = MockAirtable::start().await;
+        let account_ids = vec!["alice.near"; 2 * 1024 * 1024 / "alice.near".len()];
+        let (status, body) = mock_airtable::send(
+            &airtable.router(),
+            Request::post("/v1/kyc/batch")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "account_ids": account_ids }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error_code"], "REQUEST_TOO_LARGE");
+        assert_eq!(airtable.call_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn admin_endpoints_require_a_signature() {
+        let airtable = MockAirtable::start().await;
+        let router = secured_router(&airtable);
+        let reset_quota = |signature: &str, api_key: &str| {
+            let timestamp = chrono::Utc::now().timestamp().to_string();
+            let signature =
+                crate::signing::sign(signature, &timestamp, "POST", "/admin/quota-reset", b"");
+            mock_airtable::send(
+                &router,
+                Request::post("/admin/quota-reset")
+                    .header("X-Timestamp", timestamp)
+                    .header("X-Admin-Signature", signature)
+                    .header("X-API-Key", api_key)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        // The API keys do not grant admin access
+        assert_eq!(
+            reset_quota("key-1", "key-1").await.0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            reset_quota("admin-secret", "").await.0,
+            StatusCode::NO_CONTENT
+        );
+    }
+}
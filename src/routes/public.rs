@@ -0,0 +1,30 @@
+//! Endpoints without authentication: health checks and metrics. The legacy unversioned lookup is
+//! kept here for existing clients, but requires one of the `API_KEYS` like its `/v1` counterpart.
+
+use std::sync::Arc;
+
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+use crate::AppState;
+
+pub(super) fn router(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let legacy_router = Router::new()
+        .route("/kyc/:account_id", get(crate::get_account_kyc_status))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state,
+            super::require_api_key,
+        ));
+
+    Router::new()
+        .route("/health", get(crate::get_health))
+        .route("/metrics", get(crate::get_metrics))
+        .route("/stats", get(crate::get_stats))
+        .route("/livez", get(crate::get_liveness))
+        .route("/readyz", get(crate::get_readiness))
+        .merge(legacy_router)
+        // Authenticated by the webhook token in the handler, as Airtable cannot sign its requests
+        .route("/webhooks/airtable", post(crate::handle_airtable_webhook))
+}
@@ -0,0 +1,57 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{fetch_airtable_columns, AirtableSchemaReport, AppState, AIRTABLE_FIELD_NAMES};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(serde::Serialize)]
+struct SchemaDriftAlert<'a> {
+    alert: &'static str,
+    expected_field: &'a str,
+    actual_fields: &'a [String],
+    /// Share of the fields the proxy reads which the KYC table still has.
+    matching_score: f64,
+}
+
+/// Daily compares the columns of the KYC table with the fields the proxy reads and alerts the
+/// `ALERT_WEBHOOK_URL` about each missing one, so a renamed column is noticed before the users
+/// notice the failing lookups.
+pub(crate) async fn run_schema_drift_check(state: Arc<AppState>, alert_webhook_url: String) {
+    let client = reqwest::Client::new();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let columns =
+            match crate::circuit_breaker::in_batch_circuit(fetch_airtable_columns(&state)).await {
+                Ok(columns) => columns,
+                Err(err) => {
+                    tracing::warn!(
+                        error = err.status_and_message().1,
+                        "failed to fetch the Airtable schema for the schema drift check"
+                    );
+                    continue;
+                }
+            };
+        let report = AirtableSchemaReport::new(&columns);
+        let matching_score = 1.0 - report.missing.len() as f64 / AIRTABLE_FIELD_NAMES.len() as f64;
+        for expected_field in &report.missing {
+            tracing::error!(%expected_field, "the KYC table is missing a field the proxy reads");
+            let alert = SchemaDriftAlert {
+                alert: "schema_drift",
+                expected_field,
+                actual_fields: &columns,
+                matching_score,
+            };
+            if let Err(err) = client
+                .post(&alert_webhook_url)
+                .json(&alert)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                tracing::warn!(%expected_field, "failed to deliver the schema drift alert: {err}");
+            }
+        }
+    }
+}
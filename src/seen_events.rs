@@ -0,0 +1,36 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Mutex,
+};
+
+/// Remembers the IDs of the most recent events, so redelivered events can be detected.
+pub(crate) struct SeenEvents {
+    capacity: usize,
+    ids: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl SeenEvents {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ids: Default::default(),
+        }
+    }
+
+    /// Records the event ID, returning `false` if it has already been seen. The oldest IDs are
+    /// forgotten once the capacity is reached.
+    pub(crate) fn insert(&self, event_id: &str) -> bool {
+        let mut ids = self.ids.lock().unwrap();
+        let (seen, order) = &mut *ids;
+        if !seen.insert(event_id.to_string()) {
+            return false;
+        }
+        order.push_back(event_id.to_string());
+        if order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,205 @@
+use std::{fmt::Write, sync::Arc};
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ring::{digest, hmac};
+
+use crate::{AppState, KycError};
+
+/// How far the `X-Timestamp` of a signed request may be from the server clock.
+const MAX_TIMESTAMP_SKEW_SECS: i64 = 5 * 60;
+
+/// Admin request bodies are small JSON documents.
+const MAX_SIGNED_BODY_BYTES: usize = 1024 * 1024;
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{byte:02x}").unwrap();
+        hex
+    })
+}
+
+fn signed_message(timestamp: &str, method: &str, path: &str, body: &[u8]) -> String {
+    format!(
+        "{timestamp}{method}{path}{}",
+        to_hex(digest::digest(&digest::SHA256, body).as_ref())
+    )
+}
+
+/// Computes the hex-encoded `HMAC-SHA256(secret, timestamp + method + path + sha256(body))`
+/// signature of a request, where `path` includes the query string and the body hash is
+/// hex-encoded.
+pub(crate) fn sign(secret: &str, timestamp: &str, method: &str, path: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    to_hex(
+        hmac::sign(
+            &key,
+            signed_message(timestamp, method, path, body).as_bytes(),
+        )
+        .as_ref(),
+    )
+}
+
+/// Checks a signature computed with [`sign`] in constant time.
+pub(crate) fn verify(
+    secret: &str,
+    timestamp: &str,
+    method: &str,
+    path: &str,
+    body: &[u8],
+    signature: &str,
+) -> bool {
+    let expected = sign(secret, timestamp, method, path, body);
+    ring::constant_time::verify_slices_are_equal(
+        expected.as_bytes(),
+        signature.to_ascii_lowercase().as_bytes(),
+    )
+    .is_ok()
+}
+
+/// Rejects admin requests which are not signed with the configured admin secret, or whose
+/// `X-Timestamp` is too old to rule out a replay. Admin endpoints are disabled when the secret
+/// is not configured.
+pub(crate) async fn require_admin_signature(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(secret) = &state.config.admin_secret else {
+        return KycError::Unauthorized.into_response();
+    };
+    let headers = request.headers();
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let (Some(timestamp), Some(signature)) = (header("X-Timestamp"), header("X-Admin-Signature"))
+    else {
+        return KycError::Unauthorized.into_response();
+    };
+    match timestamp.parse::<i64>() {
+        Ok(timestamp)
+            if (chrono::Utc::now().timestamp() - timestamp).abs() <= MAX_TIMESTAMP_SKEW_SECS => {}
+        _ => return KycError::Unauthorized.into_response(),
+    }
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_SIGNED_BODY_BYTES).await else {
+        return KycError::InvalidInput("Request body is too large".to_string()).into_response();
+    };
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or_else(|| parts.uri.path());
+    if !verify(
+        secret,
+        &timestamp,
+        parts.method.as_str(),
+        path,
+        &body,
+        &signature,
+    ) {
+        return KycError::Unauthorized.into_response();
+    }
+
+    next.run(Request::from_parts(parts, body.into())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_reference_hmac() {
+        let body_hash = to_hex(digest::digest(&digest::SHA256, b"{}").as_ref());
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let expected = to_hex(
+            hmac::sign(
+                &key,
+                format!("1700000000POST/admin/rotate-key{body_hash}").as_bytes(),
+            )
+            .as_ref(),
+        );
+        assert_eq!(
+            sign("secret", "1700000000", "POST", "/admin/rotate-key", b"{}"),
+            expected
+        );
+    }
+
+    #[test]
+    fn verify_accepts_own_signatures_in_any_case() {
+        let signature = sign("secret", "1700000000", "GET", "/v1/kyc/summary?x=1", b"");
+        assert!(verify(
+            "secret",
+            "1700000000",
+            "GET",
+            "/v1/kyc/summary?x=1",
+            b"",
+            &signature
+        ));
+        assert!(verify(
+            "secret",
+            "1700000000",
+            "GET",
+            "/v1/kyc/summary?x=1",
+            b"",
+            &signature.to_ascii_uppercase()
+        ));
+    }
+
+    #[test]
+    fn verify_rejects_tampered_requests() {
+        let signature = sign("secret", "1700000000", "POST", "/admin/quota-reset", b"{}");
+        let verify = |secret, timestamp, method, path, body: &[u8]| {
+            verify(secret, timestamp, method, path, body, &signature)
+        };
+        assert!(verify(
+            "secret",
+            "1700000000",
+            "POST",
+            "/admin/quota-reset",
+            b"{}"
+        ));
+        assert!(!verify(
+            "other",
+            "1700000000",
+            "POST",
+            "/admin/quota-reset",
+            b"{}"
+        ));
+        assert!(!verify(
+            "secret",
+            "1700000001",
+            "POST",
+            "/admin/quota-reset",
+            b"{}"
+        ));
+        assert!(!verify(
+            "secret",
+            "1700000000",
+            "GET",
+            "/admin/quota-reset",
+            b"{}"
+        ));
+        assert!(!verify(
+            "secret",
+            "1700000000",
+            "POST",
+            "/admin/rotate-key",
+            b"{}"
+        ));
+        assert!(!verify(
+            "secret",
+            "1700000000",
+            "POST",
+            "/admin/quota-reset",
+            b"[]"
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use crate::KycStatus;
+
+const MAX_EVENTS_PER_ACCOUNT: usize = 100;
+const MAX_ACCOUNTS: usize = 10_000;
+
+/// Something the proxy did about an account's KYC.
+#[derive(Clone, serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum TimelineEvent {
+    /// The KYC status was looked up through the API. Only the network of the requester IP is
+    /// kept (`/24` for IPv4, `/48` for IPv6).
+    Lookup {
+        kyc_status: KycStatus,
+        requester_network: Option<String>,
+    },
+    /// A lookup returned a different status than the previous lookup.
+    StatusChanged {
+        from: KycStatus,
+        to: KycStatus,
+    },
+    PortalLinkGenerated,
+    WebhookDelivered {
+        url: String,
+    },
+    CacheEvicted,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub(crate) struct TimelineEntry {
+    /// Increasing across all the accounts, used as the pagination cursor.
+    pub id: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(flatten)]
+    pub event: TimelineEvent,
+}
+
+/// The most recent events of the most recently active accounts, kept in memory, so they do not
+/// survive a restart.
+#[derive(Default)]
+pub(crate) struct Timeline {
+    entries: Mutex<HashMap<near_account_id::AccountId, VecDeque<TimelineEntry>>>,
+    next_id: AtomicU64,
+}
+
+impl Timeline {
+    pub(crate) fn record(&self, account_id: &near_account_id::AccountId, event: TimelineEvent) {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.contains_key(account_id) && entries.len() >= MAX_ACCOUNTS {
+            let least_recently_active = entries
+                .iter()
+                .min_by_key(|(_, events)| events.back().map(|entry| entry.timestamp))
+                .map(|(account_id, _)| account_id.clone());
+            if let Some(account_id) = least_recently_active {
+                entries.remove(&account_id);
+            }
+        }
+        let events = entries.entry(account_id.clone()).or_default();
+        if events.len() == MAX_EVENTS_PER_ACCOUNT {
+            events.pop_front();
+        }
+        events.push_back(TimelineEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: chrono::Utc::now(),
+            event,
+        });
+    }
+
+    /// Events of the account, oldest first.
+    pub(crate) fn get(&self, account_id: &near_account_id::AccountId) -> Vec<TimelineEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(account_id)
+            .map(|events| events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Up to `limit` events of the account recorded after the `after` cursor, oldest first.
+    pub(crate) fn page(
+        &self,
+        account_id: &near_account_id::AccountId,
+        after: Option<u64>,
+        limit: usize,
+    ) -> Vec<TimelineEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(account_id)
+            .map(|events| {
+                events
+                    .iter()
+                    .filter(|entry| after.is_none_or(|after| entry.id > after))
+                    .take(limit)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn evict(&self, account_id: &near_account_id::AccountId) {
+        self.entries.lock().unwrap().remove(account_id);
+    }
+}
+
+/// Network of the IP address kept in the timeline, for data minimization.
+pub(crate) fn truncate_ip(ip: std::net::IpAddr) -> ipnet::IpNet {
+    let prefix_len = if ip.is_ipv4() { 24 } else { 48 };
+    ipnet::IpNet::new(ip, prefix_len)
+        .expect("the prefix length is valid")
+        .trunc()
+}
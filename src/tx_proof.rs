@@ -0,0 +1,199 @@
+use near_account_id::AccountId;
+
+use crate::{near_rpc, AppState, KycError};
+
+/// How recent the proof transaction has to be, so a leaked transaction ID cannot be reused.
+const MAX_TX_AGE: chrono::TimeDelta = chrono::TimeDelta::minutes(5);
+
+#[derive(serde::Deserialize)]
+struct TxResult {
+    transaction: Transaction,
+    transaction_outcome: TransactionOutcome,
+}
+
+#[derive(serde::Deserialize)]
+struct Transaction {
+    signer_id: AccountId,
+}
+
+#[derive(serde::Deserialize)]
+struct TransactionOutcome {
+    block_hash: String,
+}
+
+#[derive(serde::Deserialize)]
+struct BlockResult {
+    header: BlockHeader,
+}
+
+#[derive(serde::Deserialize)]
+struct BlockHeader {
+    /// Nanoseconds since the Unix epoch.
+    timestamp: i64,
+}
+
+/// Checks that the transaction was signed by the account within the last few minutes, which
+/// proves the requester controls the account.
+pub(crate) async fn verify_tx_proof(
+    state: &AppState,
+    account_id: &AccountId,
+    tx_id: &str,
+) -> Result<(), KycError> {
+    let invalid_proof =
+        |reason: &str| KycError::InvalidInput(format!("Invalid transaction proof: {reason}"));
+
+    let tx: TxResult = near_rpc::call(state, "tx", serde_json::json!([tx_id, account_id]))
+        .await?
+        .ok_or_else(|| invalid_proof("transaction was not found"))?;
+    if &tx.transaction.signer_id != account_id {
+        return Err(invalid_proof("transaction is not signed by the account"));
+    }
+
+    let block: BlockResult = near_rpc::call(
+        state,
+        "block",
+        serde_json::json!({ "block_id": tx.transaction_outcome.block_hash }),
+    )
+    .await?
+    .ok_or(KycError::NearRpcError)?;
+    let tx_time = chrono::DateTime::from_timestamp_nanos(block.header.timestamp);
+    if chrono::Utc::now() - tx_time > MAX_TX_AGE {
+        return Err(invalid_proof("transaction is older than 5 minutes"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, routing::post, Json, Router};
+
+    use super::*;
+    use crate::{
+        config::Config,
+        mock_airtable::{self, MockAirtable},
+    };
+
+    /// Starts a NEAR RPC answering `tx` with the transactions, by ID, signed by the account with
+    /// the age, and `block` with the block of the transaction.
+    async fn start_near_rpc(transactions: &[(&str, &str, chrono::TimeDelta)]) -> String {
+        let transactions = transactions
+            .iter()
+            .map(|&(tx_id, signer_id, age)| {
+                let timestamp = (chrono::Utc::now() - age).timestamp_nanos_opt().unwrap();
+                (tx_id.to_string(), (signer_id.to_string(), timestamp))
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+        let router = Router::new().route(
+            "/",
+            post(move |Json(request): Json<serde_json::Value>| async move {
+                let params = &request["params"];
+                let (tx_id, result) = match request["method"].as_str() {
+                    Some("tx") => {
+                        let tx_id = params[0].as_str().unwrap_or_default();
+                        let result = transactions.get(tx_id).map(|(signer_id, _)| {
+                            serde_json::json!({
+                                "transaction": { "signer_id": signer_id },
+                                "transaction_outcome": { "block_hash": tx_id },
+                            })
+                        });
+                        (tx_id, result)
+                    }
+                    Some("block") => {
+                        let tx_id = params["block_id"].as_str().unwrap_or_default();
+                        let result = transactions.get(tx_id).map(|(_, timestamp)| {
+                            serde_json::json!({ "header": { "timestamp": timestamp } })
+                        });
+                        (tx_id, result)
+                    }
+                    _ => ("", None),
+                };
+                Json(match result {
+                    Some(result) => serde_json::json!({ "jsonrpc": "2.0", "result": result }),
+                    None => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "error": { "name": "HANDLER_ERROR", "data": tx_id },
+                    }),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        url
+    }
+
+    async fn verify(tx_id: &str) -> Result<(), KycError> {
+        let near_rpc_url = start_near_rpc(&[
+            ("recentTx", "alice.near", chrono::TimeDelta::seconds(30)),
+            ("otherSignerTx", "bob.near", chrono::TimeDelta::seconds(30)),
+            ("oldTx", "alice.near", chrono::TimeDelta::minutes(10)),
+        ])
+        .await;
+        let state = AppState::builder()
+            .with_config(Config {
+                near_rpc_url,
+                ..Default::default()
+            })
+            .build();
+        verify_tx_proof(&state, &"alice.near".parse().unwrap(), tx_id).await
+    }
+
+    fn invalid_proof_reason(result: Result<(), KycError>) -> String {
+        match result {
+            Err(KycError::InvalidInput(reason)) => reason,
+            _ => panic!("the proof should be invalid"),
+        }
+    }
+
+    #[tokio::test]
+    async fn recent_transactions_of_the_account_are_proofs() {
+        assert!(verify("recentTx").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn transactions_of_other_signers_are_rejected() {
+        assert_eq!(
+            invalid_proof_reason(verify("otherSignerTx").await),
+            "Invalid transaction proof: transaction is not signed by the account"
+        );
+    }
+
+    #[tokio::test]
+    async fn old_transactions_are_rejected() {
+        assert_eq!(
+            invalid_proof_reason(verify("oldTx").await),
+            "Invalid transaction proof: transaction is older than 5 minutes"
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_transactions_are_rejected() {
+        assert_eq!(
+            invalid_proof_reason(verify("unknownTx").await),
+            "Invalid transaction proof: transaction was not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn lookups_require_a_proof_when_configured() {
+        let airtable = MockAirtable::start().await;
+        airtable.seed("alice.near", "approved");
+        let near_rpc_url =
+            start_near_rpc(&[("recentTx", "alice.near", chrono::TimeDelta::seconds(30))]).await;
+        let (_, router) = airtable.app(Config {
+            near_rpc_url,
+            require_tx_proof: true,
+            ..airtable.config()
+        });
+
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice.near").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = mock_airtable::get(&router, "/v1/kyc/alice.near?tx_id=oldTx").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(airtable.call_count(), 0);
+
+        let (status, body) = mock_airtable::get(&router, "/v1/kyc/alice.near?tx_id=recentTx").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["kyc_status"], "APPROVED");
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Consecutive empty results after which a view is considered broken (e.g. a filter was added to
+/// it by mistake). Accounts without KYC records also give empty results, hence the margin.
+const MAX_CONSECUTIVE_EMPTY_RESULTS: u32 = 20;
+/// How long an unhealthy view is skipped before it is tried again.
+const UNHEALTHY_VIEW_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct ViewHealth {
+    consecutive_empty_results: u32,
+    unhealthy_since: Option<Instant>,
+}
+
+/// Views with the same records which the lookups rotate through, spreading the reads.
+pub(crate) struct ViewRotationPool {
+    views: Vec<String>,
+    counter: AtomicUsize,
+    health: Mutex<Vec<ViewHealth>>,
+}
+
+impl ViewRotationPool {
+    pub(crate) fn new(views: Vec<String>) -> Self {
+        Self {
+            health: Mutex::new(views.iter().map(|_| ViewHealth::default()).collect()),
+            views,
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Picks the next healthy view round-robin, or the next view if none is healthy. Returns
+    /// `None` for an empty pool.
+    pub(crate) fn pick(&self) -> Option<&str> {
+        if self.views.is_empty() {
+            return None;
+        }
+        let health = self.health.lock().unwrap();
+        let start = self.counter.fetch_add(1, Ordering::Relaxed);
+        let index = (0..self.views.len())
+            .map(|offset| (start + offset) % self.views.len())
+            .find(|&index| {
+                health[index]
+                    .unhealthy_since
+                    .is_none_or(|since| since.elapsed() >= UNHEALTHY_VIEW_RETRY_AFTER)
+            })
+            .unwrap_or(start % self.views.len());
+        Some(&self.views[index])
+    }
+
+    /// Records whether a lookup in the view found no records.
+    pub(crate) fn record(&self, view: &str, empty: bool) {
+        let Some(index) = self.views.iter().position(|pool_view| pool_view == view) else {
+            return;
+        };
+        let mut health = self.health.lock().unwrap();
+        let view_health = &mut health[index];
+        if !empty {
+            *view_health = ViewHealth::default();
+            return;
+        }
+        view_health.consecutive_empty_results += 1;
+        if view_health.consecutive_empty_results >= MAX_CONSECUTIVE_EMPTY_RESULTS {
+            if view_health.unhealthy_since.is_none() {
+                tracing::warn!(
+                    view,
+                    "Airtable view keeps returning no records, skipping it"
+                );
+            }
+            view_health.unhealthy_since = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> ViewRotationPool {
+        ViewRotationPool::new(vec![
+            "Grid view".to_string(),
+            "Replica 1".to_string(),
+            "Replica 2".to_string(),
+        ])
+    }
+
+    #[test]
+    fn views_are_picked_round_robin() {
+        let pool = pool();
+        let picks = (0..9).map(|_| pool.pick().unwrap()).collect::<Vec<_>>();
+        assert_eq!(picks, ["Grid view", "Replica 1", "Replica 2"].repeat(3));
+    }
+
+    #[test]
+    fn views_returning_no_records_are_skipped() {
+        let pool = pool();
+        for _ in 0..MAX_CONSECUTIVE_EMPTY_RESULTS {
+            pool.record("Replica 1", true);
+        }
+        let picks = (0..4).map(|_| pool.pick().unwrap()).collect::<Vec<_>>();
+        assert_eq!(picks, ["Grid view", "Replica 2", "Replica 2", "Grid view"]);
+
+        pool.record("Replica 1", false);
+        assert!(pool.health.lock().unwrap()[1].unhealthy_since.is_none());
+    }
+
+    #[test]
+    fn empty_pools_pick_no_view() {
+        assert_eq!(ViewRotationPool::new(Vec::new()).pick(), None);
+    }
+}
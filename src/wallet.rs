@@ -0,0 +1,249 @@
+use std::{fmt, str::FromStr};
+
+use near_account_id::AccountId;
+
+/// 20-byte Ethereum address, written as `0x` followed by 40 hex digits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct EthAddress([u8; 20]);
+
+/// 32-byte Solana address (an ed25519 public key), written in base58.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct SolAddress([u8; 32]);
+
+/// Wallet address on one of the supported chains.
+///
+/// Anything which is a valid NEAR account ID parses as a NEAR account. In particular, lowercase
+/// `0x` addresses are NEAR Ethereum-implicit accounts, so only checksummed (mixed-case) Ethereum
+/// addresses parse as [`WalletAddress::Eth`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum WalletAddress {
+    Near(AccountId),
+    Eth(EthAddress),
+    Sol(SolAddress),
+}
+
+impl FromStr for EthAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let hex = value
+            .strip_prefix("0x")
+            .filter(|hex| hex.len() == 40 && hex.bytes().all(|char| char.is_ascii_hexdigit()))
+            .ok_or_else(|| anyhow::anyhow!("expected `0x` followed by 40 hex digits"))?;
+        let mut bytes = [0; 20];
+        for (byte, index) in bytes.iter_mut().zip((0..hex.len()).step_by(2)) {
+            *byte = u8::from_str_radix(&hex[index..index + 2], 16)?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Decodes a base58 (Bitcoin alphabet) string.
+pub(crate) fn decode_base58(value: &str) -> Option<Vec<u8>> {
+    // Little-endian base-256 digits of the decoded number
+    let mut bytes: Vec<u8> = Vec::new();
+    for char in value.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|digit| *digit == char)? as u32;
+        for byte in &mut bytes {
+            carry += u32::from(*byte) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    // Every leading `1` stands for a leading zero byte
+    let leading_zeros = value.bytes().take_while(|char| *char == b'1').count();
+    bytes.extend(std::iter::repeat_n(0, leading_zeros));
+    bytes.reverse();
+    Some(bytes)
+}
+
+/// Encodes bytes as a base58 (Bitcoin alphabet) string.
+pub(crate) fn encode_base58(bytes: &[u8]) -> String {
+    // Little-endian base-58 digits of the number
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in bytes {
+        let mut carry = u32::from(*byte);
+        for digit in &mut digits {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let leading_zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+    std::iter::repeat_n(b'1', leading_zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[*digit as usize]),
+        )
+        .map(char::from)
+        .collect()
+}
+
+impl FromStr for SolAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        decode_base58(value)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Self)
+            .ok_or_else(|| anyhow::anyhow!("expected a base58-encoded 32-byte address"))
+    }
+}
+
+impl FromStr for WalletAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Ok(account_id) = value.parse() {
+            Ok(Self::Near(account_id))
+        } else if let Ok(address) = value.parse() {
+            Ok(Self::Eth(address))
+        } else if let Ok(address) = value.parse() {
+            Ok(Self::Sol(address))
+        } else {
+            Err(anyhow::anyhow!(
+                "not a NEAR account ID, Ethereum address or Solana address: {value}"
+            ))
+        }
+    }
+}
+
+impl fmt::Display for EthAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("0x")?;
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl fmt::Display for SolAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_base58(&self.0))
+    }
+}
+
+impl fmt::Display for WalletAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Near(account_id) => account_id.fmt(f),
+            Self::Eth(address) => address.fmt(f),
+            Self::Sol(address) => address.fmt(f),
+        }
+    }
+}
+
+impl From<AccountId> for WalletAddress {
+    fn from(account_id: AccountId) -> Self {
+        Self::Near(account_id)
+    }
+}
+
+impl serde::Serialize for WalletAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for WalletAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Deserializes a list of wallet addresses, collecting all the invalid ones instead of failing
+/// on the first one, so they can all be reported back.
+pub(crate) fn deserialize_wallet_addresses<'de, D>(
+    deserializer: D,
+) -> Result<Result<Vec<WalletAddress>, Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut wallet_addresses = Vec::new();
+    let mut invalid_addresses = Vec::new();
+    for address in <Vec<String> as serde::Deserialize>::deserialize(deserializer)? {
+        match address.parse() {
+            Ok(wallet_address) => wallet_addresses.push(wallet_address),
+            Err(_) => invalid_addresses.push(address),
+        }
+    }
+    if invalid_addresses.is_empty() {
+        Ok(Ok(wallet_addresses))
+    } else {
+        Ok(Err(invalid_addresses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base58_round_trips() {
+        for bytes in [&[][..], &[0], &[0, 0, 1], &[255; 32], b"hello world"] {
+            assert_eq!(decode_base58(&encode_base58(bytes)).as_deref(), Some(bytes));
+        }
+    }
+
+    #[test]
+    fn base58_matches_bitcoin_alphabet() {
+        assert_eq!(encode_base58(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(encode_base58(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), "11233QC4");
+        assert_eq!(
+            decode_base58("11233QC4"),
+            Some(vec![0, 0, 0x28, 0x7f, 0xb4, 0xcd])
+        );
+    }
+
+    #[test]
+    fn base58_rejects_characters_outside_the_alphabet() {
+        for value in ["0", "O", "I", "l", "abc+"] {
+            assert_eq!(decode_base58(value), None);
+        }
+    }
+
+    #[test]
+    fn addresses_parse_by_chain() {
+        assert!(matches!(
+            "alice.near".parse::<WalletAddress>().unwrap(),
+            WalletAddress::Near(_)
+        ));
+        // Lowercase `0x` addresses are NEAR Ethereum-implicit accounts
+        assert!(matches!(
+            "0x52908400098527886e0f7030069857d2e4169ee7"
+                .parse::<WalletAddress>()
+                .unwrap(),
+            WalletAddress::Near(_)
+        ));
+        let eth = "0x52908400098527886E0F7030069857D2E4169EE7"
+            .parse::<WalletAddress>()
+            .unwrap();
+        assert!(matches!(eth, WalletAddress::Eth(_)));
+        assert_eq!(
+            eth.to_string(),
+            "0x52908400098527886e0f7030069857d2e4169ee7"
+        );
+
+        // Uppercase base58 is not a valid NEAR account ID
+        let sol = "So11111111111111111111111111111111111111112"
+            .parse::<WalletAddress>()
+            .unwrap();
+        assert!(matches!(sol, WalletAddress::Sol(_)));
+        assert_eq!(
+            sol.to_string(),
+            "So11111111111111111111111111111111111111112"
+        );
+        assert!("not a wallet!".parse::<WalletAddress>().is_err());
+    }
+}